@@ -1,47 +1,27 @@
-export interface Image {
-	url: string;
-	alt: string;
-	caption: string;
-}
+// The types are generated from substuff's Rust structs, see generated.ts
+import type { Article } from './generated';
 
-export interface Author {
-	name: string;
-	authorBio: string;
-	slug: string;
-}
-
-export interface Professor {
-	name: string;
-	professorBio: string;
-	slug: string;
-}
-
-export interface Question {
-	question: string;
-	answers: string[];
-	correct_answer: string;
-}
-
-export interface Article {
-	title: string;
-	description: string;
-	body: string;
-	category: string;
-	image: Image;
-	authors: Author[];
-	professor: Professor;
-	questions: Question[]; // Will be Some(Vec<Question>) or Some([]) in Rust
-	createdAt: string; // ISO string
-	publishedAt: string; // ISO string
-	readingTime: number;
-	updatedAt: string | null; // ISO string or null
-	lastUpdatedAt: string | null; // ISO string or null
-}
+export type {
+	Article,
+	ArticleChange,
+	ArticleSummary,
+	Author,
+	BodyFormat,
+	Diagnostic,
+	Image,
+	LoadedArticle,
+	MigrationSummary,
+	Professor,
+	Question,
+	ReadingStats,
+	Severity
+} from './generated';
 
 export const defaultArticle: Article = {
 	title: 'Default Article Title',
 	description: 'A default description for the article.',
 	body: '<p>This is the default <strong>HTML body</strong> content.</p><h2>Subtitle</h2><p>More content here.</p>',
+	bodyFormat: 'html',
 	category: 'General',
 	image: {
 		url: 'https://placehold.co/600x400/EEE/31343C?text=Default+Image',
@@ -77,4 +57,4 @@ export const defaultArticle: Article = {
 	readingTime: 5,
 	updatedAt: null,
 	lastUpdatedAt: null
-};
\ No newline at end of file
+};
@@ -0,0 +1,115 @@
+// Generated from the Rust types in substuff, don't edit.
+// Run `cargo run --bin generate_types` in tauri-app/src-tauri/src/substuff to update.
+
+/**
+ * An article file in the website repo. Dates are read leniently, see
+ * `parse_timestamp`, and written as ISO-8601 in UTC.
+ */
+export type Article = { 
+/**
+ * Which version of this shape the file was written in, see `migrate`.
+ */
+schemaVersion?: number, title: string, description: string, body: string, 
+/**
+ * HTML when missing.
+ */
+bodyFormat?: BodyFormat, 
+/**
+ * `body` as the website shows it, filled in by `render_body_html`.
+ */
+bodyHtml?: string, image: Image, authors: Array<Author>, professor: Professor, questions?: Array<Question> | null, createdAt: string, publishedAt: string, 
+/**
+ * In minutes.
+ */
+readingTime: number, updatedAt?: string | null, lastUpdatedAt?: string | null, category: string, };
+
+/**
+ * What `Article.body` is written in. Articles from before Markdown support
+ * have no `bodyFormat` and are HTML.
+ */
+export type BodyFormat = "html" | "markdown";
+
+export type Image = { url: string, alt: string, caption: string, };
+
+export type Author = { name: string, authorBio: string, slug: string, };
+
+export type Professor = { name: string, professorBio: string, slug: string, };
+
+export type Question = { question: string, answers: Array<string>, correct_answer: string, };
+
+/**
+ * The columns of the article table, without the body.
+ */
+export type ArticleSummary = { 
+/**
+ * Relative to the repo root, `/` separated.
+ */
+path: string, 
+/**
+ * Blob SHA of the file, pass it back as `sha` when uploading changes.
+ * Empty for a local file that isn't committed yet.
+ */
+sha: string, title: string, category: string, authors: Array<string>, published_at: string, };
+
+/**
+ * One article file, parsed, with the blob SHA it was read at.
+ */
+export type LoadedArticle = { path: string, 
+/**
+ * `path` relative to the articles dir, the `file_name` uploads take.
+ */
+file_name: string, 
+/**
+ * `None` for a local file that isn't committed yet, it uploads as a new
+ * article.
+ */
+sha: string | null, article: Article, };
+
+/**
+ * What `delete_article` or `rename_article` did, enough to undo it.
+ */
+export type ArticleChange = { "action": "deleted", path: string, 
+/**
+ * Blob SHA of the deleted file, its content can still be fetched.
+ */
+sha: string, commit_sha: string, message: string, } | { "action": "renamed", from_path: string, to_path: string, sha: string, commit_sha: string, message: string, };
+
+/**
+ * One problem with an article.
+ */
+export type Diagnostic = { severity: Severity, 
+/**
+ * Path of the field using the TOML names, e.g. `questions[1].correct_answer`.
+ */
+field: string, message: string, };
+
+/**
+ * How bad a `Diagnostic` is. Only `Error` stops an upload.
+ */
+export type Severity = "info" | "warning" | "error";
+
+/**
+ * What the reading time of a body is made of.
+ */
+export type ReadingStats = { words: number, formulas: number, figures: number, 
+/**
+ * Rounded up, 0 only for an empty body.
+ */
+minutes: number, };
+
+/**
+ * What `migrate_remote_articles` did.
+ */
+export type MigrationSummary = { 
+/**
+ * Articles rewritten at `CURRENT_SCHEMA_VERSION`.
+ */
+migrated: Array<string>, up_to_date: number, 
+/**
+ * Files that aren't articles even after migrating, left as they are.
+ */
+unreadable: Array<string>, 
+/**
+ * The forge's message for the commit, `None` if nothing needed migrating.
+ */
+committed: string | null, };
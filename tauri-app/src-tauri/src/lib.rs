@@ -1,34 +1,172 @@
-use log;
-use std::{path::Path, sync::Mutex};
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 use substuff::*;
 use tauri::{
     ipc::InvokeError, path, AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder,
-}; // Ensure log is imported
+};
 
 #[derive(Default, Debug)]
 struct AppState {
     github_key: String,
     github_intermediate: GithubDeviceCodeResponse,
+    website_repo: Option<PathBuf>,
+    git_sync: Option<GitProgressHandler>,
+    auth_poll: Option<AuthPollHandler>,
+    config: AppConfig,
+    token_store: Option<TokenStore>,
+    /// Shared by every request to the forge so connections are pooled.
+    http: reqwest::Client,
+}
+
+fn app_config(app: &AppHandle) -> AppConfig {
+    let state = app.state::<Mutex<AppState>>();
+    let locked_state = state.lock().unwrap();
+    locked_state.config.clone()
+}
+
+/// GitHub or Gitea backend for the configured repo.
+fn app_forge(app: &AppHandle) -> Box<dyn Forge> {
+    let state = app.state::<Mutex<AppState>>();
+    let locked_state = state.lock().unwrap();
+    forge_for_config(locked_state.config.clone(), locked_state.http.clone())
+}
+
+/// Keeps a freshly obtained token in memory and persists it for next launch.
+/// Failing to persist only costs the user a login next time, so it's logged
+/// rather than failing the login.
+fn remember_token(app: &AppHandle, token: &str) {
+    let state = app.state::<Mutex<AppState>>();
+    let mut locked_state = state.lock().unwrap();
+    locked_state.github_key = token.to_string();
+    if let Some(token_store) = &locked_state.token_store {
+        match token_store.save(token) {
+            Ok(backend) => log::info!("Token persisted ({:?})", backend),
+            Err(e) => log::error!("Failed to persist token: {:#}", e),
+        }
+    }
+}
+
+/// Drops the token from memory and from disk.
+fn forget_token(app: &AppHandle) -> String {
+    let state = app.state::<Mutex<AppState>>();
+    let mut locked_state = state.lock().unwrap();
+    forget_locked_token(&mut locked_state)
+}
+
+fn forget_locked_token(locked_state: &mut AppState) -> String {
+    if let Some(token_store) = &locked_state.token_store {
+        if let Err(e) = token_store.clear() {
+            log::error!("Failed to wipe the saved token: {:#}", e);
+        }
+    }
+    std::mem::take(&mut locked_state.github_key)
+}
+
+/// `forget_token`, unless the user has logged in again since `token` was
+/// read and it's no longer the one in use. Returns whether it was forgotten.
+fn forget_token_if_current(app: &AppHandle, token: &str) -> bool {
+    let state = app.state::<Mutex<AppState>>();
+    let mut locked_state = state.lock().unwrap();
+    if locked_state.github_key != token {
+        return false;
+    }
+    forget_locked_token(&mut locked_state);
+    true
+}
+
+/// Checks a token restored at startup against the forge. Only a definite
+/// 401 wipes it, being offline shouldn't log the user out.
+async fn validate_restored_token(app: AppHandle, token: String) {
+    match app_forge(&app).commit_author(&token).await {
+        Ok(author) => log::info!("Restored session for {}", author.name),
+        Err(GitHubError::Unauthorized { .. }) => {
+            if !forget_token_if_current(&app, &token) {
+                log::info!("Saved token was rejected, but it was already replaced by a new login");
+                return;
+            }
+            log::warn!("Saved token was rejected, logging out");
+            if let Err(e) = app.emit("auth-expired", ()) {
+                log::warn!("Failed to emit auth-expired: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Could not validate the saved token, keeping it: {}", e),
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
-            app.manage(Mutex::new(AppState::default()));
+            let config_dir = path::PathResolver::app_config_dir(app.path())?;
+            log::info!("App config dir: {:?}", config_dir); // Use log macro
 
-            let path_resolver = path::PathResolver::app_config_dir(app.path());
-            log::info!("App config dir: {:?}", path_resolver); // Use log macro
+            // A broken config file should stop the app rather than quietly
+            // publishing to the default repo
+            let config = AppConfig::load(&config_dir)?;
+            log::info!(
+                "Targeting {}/{} ({}), articles in {}",
+                config.owner,
+                config.repo,
+                config.branch,
+                config.articles_dir
+            );
+            let token_store = TokenStore::new(&config_dir, &config);
+            let github_key = match token_store.load() {
+                Ok(token) => token.unwrap_or_default(),
+                Err(e) => {
+                    log::error!("Could not load the saved token: {:#}", e);
+                    String::new()
+                }
+            };
+            let restored_token = github_key.clone();
+            app.manage(Mutex::new(AppState {
+                github_key,
+                config,
+                token_store: Some(token_store),
+                ..Default::default()
+            }));
+            // Only once the state is managed, the check reads it
+            if !restored_token.is_empty() {
+                tauri::async_runtime::spawn(validate_restored_token(
+                    app.handle().clone(),
+                    restored_token,
+                ));
+            }
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_article,
+            validate_article,
+            render_article_body,
+            article_reading_stats,
             new_window,
             test_auth,
             start_auth,
             wait_for_auth,
-            upload_article_to_github // Added new command
+            upload_article_to_github, // Added new command
+            upload_article_as_pull_request,
+            sync_git_in_cache,
+            cancel_git_sync,
+            cancel_auth,
+            publish_local_changes,
+            get_github_rate_limit,
+            get_app_config,
+            login_with_token,
+            restore_session,
+            logout,
+            whoami,
+            list_remote_articles,
+            migrate_all_articles,
+            get_remote_article,
+            list_local_articles,
+            get_local_article,
+            delete_article,
+            rename_article,
+            undo_article_change
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -43,6 +181,24 @@ fn get_article(file_path: String) -> Result<Article, String> {
     }
 }
 
+/// Live feedback for the editor, the same checks uploads run.
+#[tauri::command]
+fn validate_article(article: Article) -> Vec<Diagnostic> {
+    validate(&article)
+}
+
+/// The sanitized HTML an article body publishes as, for the editor preview.
+#[tauri::command]
+fn render_article_body(body: String, body_format: BodyFormat) -> String {
+    render_body(&body, body_format)
+}
+
+/// Word count and reading time of a body, for the editor.
+#[tauri::command]
+fn article_reading_stats(body: String, body_format: BodyFormat) -> ReadingStats {
+    reading_stats(&body, body_format)
+}
+
 #[tauri::command]
 fn new_window(app: AppHandle) {
     WebviewWindowBuilder::new(
@@ -62,7 +218,11 @@ struct Payload {
 
 #[tauri::command]
 async fn test_auth(app: AppHandle) -> Result<(), InvokeError> {
-    let device_code_response = start_git_auth().map_err(|e| InvokeError::from(e.to_string()))?;
+    let forge = app_forge(&app);
+    let device_code_response = forge
+        .start_device_flow()
+        .await
+        .map_err(|e| InvokeError::from(e.to_string()))?;
 
     app.emit(
         "auth-started",
@@ -80,14 +240,22 @@ async fn test_auth(app: AppHandle) -> Result<(), InvokeError> {
         device_code_response.user_code
     );
 
-    let config = GitHubConfig::new(device_code_response);
-    match substuff::wait_for_github(config).await {
+    let poll = begin_auth_poll(&app);
+    let result = forge
+        .wait_for_device_token(device_code_response, &poll)
+        .await;
+    end_auth_poll(&app, &poll);
+    match result {
         Ok(token) => {
             log::info!("test_auth: Token received successfully."); // Use log macro
             app.emit("auth-responded", token)
                 .map_err(|e| InvokeError::from(format!("Failed to emit auth-responded: {}", e)))?;
             Ok(())
         }
+        Err(OAuthError::Cancelled) => {
+            log::info!("test_auth: cancelled by the user");
+            Err(InvokeError::from("Login was cancelled."))
+        }
         Err(err) => {
             log::error!("test_auth: Error during GitHub polling: {:?}", err); // Use log macro
             app.emit("auth-responded", String::new()).map_err(|e| {
@@ -98,21 +266,69 @@ async fn test_auth(app: AppHandle) -> Result<(), InvokeError> {
     }
 }
 
+/// Registers a new device flow poll that reports to "auth-progress",
+/// cancelling one still running from an earlier login attempt.
+fn begin_auth_poll(app: &AppHandle) -> AuthPollHandler {
+    let progress_app = app.clone();
+    let poll = AuthPollHandler::new(move |progress| {
+        if let Err(e) = progress_app.emit("auth-progress", &progress) {
+            log::warn!("Failed to emit auth-progress: {}", e);
+        }
+    });
+    let state = app.state::<Mutex<AppState>>();
+    if let Some(previous) = state.lock().unwrap().auth_poll.replace(poll.clone()) {
+        previous.cancel();
+    }
+    poll
+}
+
+fn end_auth_poll(app: &AppHandle, poll: &AuthPollHandler) {
+    let state = app.state::<Mutex<AppState>>();
+    let mut locked_state = state.lock().unwrap();
+    if locked_state
+        .auth_poll
+        .as_ref()
+        .is_some_and(|current| current.is_same(poll))
+    {
+        locked_state.auth_poll = None;
+    }
+}
+
+#[tauri::command]
+fn cancel_auth(app: AppHandle) -> bool {
+    let state = app.state::<Mutex<AppState>>();
+    let locked_state = state.lock().unwrap();
+    match &locked_state.auth_poll {
+        Some(poll) => {
+            log::info!("cancel_auth: cancelling the device flow polling");
+            poll.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
 #[tauri::command]
-fn start_auth(app: AppHandle) -> Result<Payload, InvokeError> {
-    let device_code_response = start_git_auth().map_err(|e| InvokeError::from(e.to_string()))?;
+async fn start_auth(app: AppHandle) -> Result<Payload, InvokeError> {
+    let forge = app_forge(&app);
+    let device_code_response = forge
+        .start_device_flow()
+        .await
+        .map_err(|e| InvokeError::from(e.to_string()))?;
     let payload = Payload {
         verification_uri: device_code_response.verification_uri.clone(),
         user_code: device_code_response.user_code.clone(),
     };
-    let state = app.state::<Mutex<AppState>>();
-    let mut locked_state = state.lock().unwrap();
-    locked_state.github_intermediate = device_code_response.clone();
-    log::info!(
-        // Use log macro
-        "start_auth: AppState github_intermediate updated: {:#?}",
-        locked_state.github_intermediate
-    );
+    {
+        let state = app.state::<Mutex<AppState>>();
+        let mut locked_state = state.lock().unwrap();
+        locked_state.github_intermediate = device_code_response.clone();
+        log::info!(
+            // Use log macro
+            "start_auth: AppState github_intermediate updated: {:#?}",
+            locked_state.github_intermediate
+        );
+    }
 
     app.emit("auth-started", payload.clone())
         .map_err(|e| InvokeError::from(e.to_string()))?;
@@ -138,20 +354,25 @@ async fn wait_for_auth(app: AppHandle) -> Result<String, InvokeError> {
         device_code_response.user_code
     );
 
-    let config = GitHubConfig::new(device_code_response);
-    match substuff::wait_for_github(config).await {
+    let forge = app_forge(&app);
+    let poll = begin_auth_poll(&app);
+    let result = forge
+        .wait_for_device_token(device_code_response, &poll)
+        .await;
+    end_auth_poll(&app, &poll);
+    match result {
         Ok(token) => {
             log::info!("wait_for_auth: Token received successfully."); // Use log macro
             app.emit("auth-responded", &token)
                 .map_err(|e| InvokeError::from(format!("Failed to emit auth-responded: {}", e)))?;
-            {
-                let mut inner_state = state.lock().unwrap();
-                inner_state.github_key = token.clone();
-                log::info!("wait_for_auth: AppState updated with GitHub key."); // Use log macro
-            }
-            println!("{}", token);
+            remember_token(&app, &token);
+            log::info!("wait_for_auth: signed in");
             Ok(token)
         }
+        Err(OAuthError::Cancelled) => {
+            log::info!("wait_for_auth: cancelled by the user");
+            Err(InvokeError::from("Login was cancelled."))
+        }
         Err(err) => {
             log::error!("wait_for_auth: Error during GitHub polling: {:?}", err); // Use log macro
             app.emit("auth-responded", String::new()).map_err(|e| {
@@ -165,11 +386,39 @@ async fn wait_for_auth(app: AppHandle) -> Result<String, InvokeError> {
     }
 }
 
+/// Extra file committed together with an article, e.g. its image or an
+/// author profile. `file_name` is relative to the configured assets dir.
+#[derive(serde::Deserialize, Debug)]
+struct ArticleAsset {
+    file_name: String,
+    content_base64: String,
+}
+
+impl ArticleAsset {
+    fn into_upload(self, config: &AppConfig) -> Result<GitHubFileUpload, InvokeError> {
+        let valid_name = self
+            .file_name
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..");
+        if !valid_name {
+            return Err(InvokeError::from(format!(
+                "{:?} is not a file name inside the assets dir",
+                self.file_name
+            )));
+        }
+        GitHubFileUpload::from_base64(config.asset_path(&self.file_name), &self.content_base64)
+            .map_err(|e| InvokeError::from(format!("{:#}", e)))
+    }
+}
+
 #[tauri::command]
 async fn upload_article_to_github(
     app: AppHandle,
-    article: substuff::Article, // This is substuff::Article
+    mut article: substuff::Article, // This is substuff::Article
     file_name: String,
+    sha: Option<String>, // Blob SHA the article was loaded from, None for a new article
+    assets: Option<Vec<ArticleAsset>>,
+    compute_reading_time: Option<bool>, // Replace readingTime with the one computed from the body
 ) -> Result<String, InvokeError> {
     log::info!("Attempting to upload article: {}", article.title);
 
@@ -191,9 +440,21 @@ async fn upload_article_to_github(
         &github_token[..std::cmp::min(5, github_token.len())]
     );
 
-    let owner = "thebeakers";
-    let repo_name = "TheBeakersWebsite";
-    let path_in_repo = format!("src/articles/{}", file_name);
+    if let Err(invalid) = ensure_article_valid(&article) {
+        log::error!("Refusing to upload article: {}", invalid);
+        return Err(InvokeError::from(invalid));
+    }
+
+    if sha.is_some() {
+        article.mark_updated();
+    }
+    if compute_reading_time.unwrap_or(false) {
+        article.update_reading_time();
+    }
+    article.render_body_html();
+
+    let forge = app_forge(&app);
+    let path_in_repo = forge.config().article_path(&file_name);
     let commit_message = format!("docs: add/update article '{}' via editor", article.title);
 
     let toml_content = match toml::to_string_pretty(&article) {
@@ -213,23 +474,586 @@ async fn upload_article_to_github(
         toml_content
     );
 
-    match substuff::upload_file_to_github(
+    let assets = assets.unwrap_or_default();
+    let upload_result = if assets.is_empty() {
+        forge
+            .put_file(
+                &github_token,
+                &path_in_repo,
+                &commit_message,
+                &toml_content,
+                sha.as_deref(),
+            )
+            .await
+    } else {
+        // Article and assets go in as one commit so they can't land half way
+        let mut files = vec![GitHubFileUpload::text(path_in_repo, &toml_content)];
+        for asset in assets {
+            files.push(asset.into_upload(forge.config())?);
+        }
+        // The Git Data API has no SHA precondition per file, check it up front
+        forge
+            .ensure_file_unchanged(&github_token, &files[0].path, sha.as_deref())
+            .await?;
+        forge
+            .commit_files(
+                &github_token,
+                &forge.config().branch,
+                &commit_message,
+                &files,
+            )
+            .await
+    };
+
+    match upload_result {
+        Ok(success_message) => {
+            log::info!("Successfully uploaded article: {}", success_message);
+            Ok(success_message)
+        }
+        Err(upload_error) => {
+            log::error!("Failed to upload article: {}", upload_error);
+            Err(InvokeError::from(upload_error))
+        }
+    }
+}
+
+/// Commits the article to an `article/<slug>` branch and opens (or updates)
+/// a pull request for professor review. Returns the PR URL.
+#[tauri::command]
+async fn upload_article_as_pull_request(
+    app: AppHandle,
+    mut article: substuff::Article,
+    file_name: String,
+    assets: Option<Vec<ArticleAsset>>,
+    compute_reading_time: Option<bool>,
+) -> Result<String, InvokeError> {
+    log::info!("Submitting article for review: {}", article.title);
+
+    let github_token = {
+        let state = app.state::<Mutex<AppState>>();
+        let locked_state = state.lock().unwrap();
+        locked_state.github_key.clone()
+    };
+
+    if github_token.is_empty() {
+        log::error!("GitHub token is missing. User needs to authenticate.");
+        return Err(InvokeError::from(
+            "GitHub token is missing. Please authenticate first.",
+        ));
+    }
+
+    let forge = app_forge(&app);
+    let path_in_repo = forge.config().article_path(&file_name);
+    let commit_message = format!("docs: add/update article '{}' via editor", article.title);
+
+    // Stamp it if the article is already on the configured branch
+    if forge
+        .get_file(&github_token, &path_in_repo)
+        .await?
+        .is_some()
+    {
+        article.mark_updated();
+    }
+    if compute_reading_time.unwrap_or(false) {
+        article.update_reading_time();
+    }
+    article.render_body_html();
+
+    let toml_content = toml::to_string_pretty(&article).map_err(|e| {
+        log::error!("Failed to serialize article to TOML: {}", e);
+        InvokeError::from(format!("Failed to serialize article to TOML: {}", e))
+    })?;
+
+    let mut files = vec![GitHubFileUpload::text(path_in_repo, &toml_content)];
+    for asset in assets.unwrap_or_default() {
+        files.push(asset.into_upload(forge.config())?);
+    }
+
+    match substuff::upload_article_as_pull_request(
+        forge.as_ref(),
         &github_token,
-        owner,
-        repo_name,
-        &path_in_repo,
+        &article,
+        &file_name,
         &commit_message,
-        &toml_content,
+        &files,
     )
     .await
     {
-        Ok(success_message) => {
-            log::info!("Successfully uploaded article: {}", success_message);
-            Ok(success_message)
+        Ok(pull_request) => {
+            log::info!(
+                "Article is up for review in PR #{}: {}",
+                pull_request.number,
+                pull_request.html_url
+            );
+            Ok(pull_request.html_url)
         }
         Err(error_message) => {
-            log::error!("Failed to upload article: {}", error_message);
+            log::error!("Failed to submit article for review: {}", error_message);
             Err(InvokeError::from(error_message))
         }
     }
 }
+
+#[derive(serde::Serialize, Clone)]
+struct SyncStatus {
+    stage: String,
+    message: String,
+}
+
+fn emit_sync_status(app: &AppHandle, stage: &str, message: String) {
+    log::info!("sync_git_in_cache [{}]: {}", stage, message);
+    if let Err(e) = app.emit(
+        "git-sync",
+        SyncStatus {
+            stage: stage.to_string(),
+            message,
+        },
+    ) {
+        log::warn!("Failed to emit git-sync: {}", e);
+    }
+}
+
+const GIT_PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(100);
+
+fn git_progress_emitter(app: &AppHandle) -> GitProgressHandler {
+    let app = app.clone();
+    // libgit2 reports every object, so throttle what we send over IPC
+    let last_emit = Mutex::new(Instant::now() - GIT_PROGRESS_EVENT_INTERVAL);
+    GitProgressHandler::new(move |progress| {
+        let finished = match &progress {
+            GitProgress::Transfer {
+                received_objects,
+                total_objects,
+                indexed_deltas,
+                total_deltas,
+                ..
+            } => received_objects == total_objects && indexed_deltas == total_deltas,
+            GitProgress::Checkout {
+                completed, total, ..
+            } => completed == total,
+            GitProgress::Push { current, total, .. } => current == total,
+        };
+        let mut last_emit = last_emit.lock().unwrap();
+        if !finished && last_emit.elapsed() < GIT_PROGRESS_EVENT_INTERVAL {
+            return;
+        }
+        *last_emit = Instant::now();
+        if let Err(e) = app.emit("git-progress", progress) {
+            log::warn!("Failed to emit git-progress: {}", e);
+        }
+    })
+}
+
+#[derive(serde::Serialize, Clone)]
+struct CacheSyncSummary {
+    #[serde(flatten)]
+    articles: LocalArticlesSummary,
+    sync: SyncReport,
+}
+
+fn sync_website_repo(
+    app: &AppHandle,
+    config: &AppConfig,
+    repo_path: &Path,
+    progress: &GitProgressHandler,
+) -> anyhow::Result<CacheSyncSummary> {
+    emit_sync_status(
+        app,
+        "cloning",
+        format!("Opening or cloning the website repo at {:?}", repo_path),
+    );
+    let mut repo = ensure_repo_is_healthy(&config.clone_url(), repo_path, progress)?;
+
+    emit_sync_status(
+        app,
+        "fetching",
+        format!("Fetching origin/{}", config.branch),
+    );
+    let sync = update_repo_preserving_changes(&mut repo, &config.branch, progress)?;
+    if let Some(draft_branch) = &sync.draft_branch {
+        emit_sync_status(
+            app,
+            "preserved",
+            format!("Local edits were saved to branch {}", draft_branch),
+        );
+    }
+    if !sync.conflicts.is_empty() {
+        emit_sync_status(
+            app,
+            "conflicts",
+            format!(
+                "These files changed both locally and upstream: {:?}",
+                sync.conflicts
+            ),
+        );
+    }
+
+    emit_sync_status(app, "scanning", "Looking for local articles".to_string());
+    let articles = find_local_articles(repo_path, &config.articles_dir)?;
+
+    emit_sync_status(
+        app,
+        "done",
+        format!("Found {} articles", articles.articles.len()),
+    );
+    Ok(CacheSyncSummary { articles, sync })
+}
+
+#[tauri::command]
+async fn sync_git_in_cache(app: AppHandle) -> Result<CacheSyncSummary, InvokeError> {
+    let cache_dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| InvokeError::from(format!("Could not resolve app cache dir: {}", e)))?;
+    let config = app_config(&app);
+    // Keyed by owner too, so pointing the config at a fork gets its own clone
+    let repo_path = cache_dir.join(&config.owner).join(&config.repo);
+
+    let progress = git_progress_emitter(&app);
+    {
+        let state = app.state::<Mutex<AppState>>();
+        let mut locked_state = state.lock().unwrap();
+        if locked_state.git_sync.is_some() {
+            return Err(InvokeError::from("A sync is already in progress."));
+        }
+        locked_state.git_sync = Some(progress.clone());
+    }
+
+    // git2 is blocking, keep it off the async runtime
+    let worker_app = app.clone();
+    let worker_progress = progress.clone();
+    let summary = tauri::async_runtime::spawn_blocking(move || {
+        sync_website_repo(&worker_app, &config, &repo_path, &worker_progress)
+    })
+    .await;
+
+    let state = app.state::<Mutex<AppState>>();
+    state.lock().unwrap().git_sync = None;
+    let summary = summary.map_err(|e| InvokeError::from(format!("Sync task failed: {}", e)))?;
+
+    match summary {
+        Ok(summary) => {
+            state.lock().unwrap().website_repo = Some(summary.articles.repo_path.clone());
+            Ok(summary)
+        }
+        Err(_) if progress.is_cancelled() => {
+            log::info!("sync_git_in_cache: cancelled by the user");
+            emit_sync_status(&app, "cancelled", "Sync was cancelled".to_string());
+            Err(InvokeError::from("Sync was cancelled."))
+        }
+        Err(e) => {
+            log::error!("sync_git_in_cache: {:#}", e);
+            emit_sync_status(&app, "error", format!("{:#}", e));
+            Err(InvokeError::from(format!(
+                "Failed to sync website repo: {:#}",
+                e
+            )))
+        }
+    }
+}
+
+#[tauri::command]
+fn cancel_git_sync(app: AppHandle) -> bool {
+    let state = app.state::<Mutex<AppState>>();
+    let locked_state = state.lock().unwrap();
+    match &locked_state.git_sync {
+        Some(progress) => {
+            log::info!("cancel_git_sync: cancelling the running sync");
+            progress.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+#[tauri::command]
+async fn publish_local_changes(
+    app: AppHandle,
+    message: Option<String>,
+) -> Result<PublishResult, InvokeError> {
+    let (github_token, repo_path) = {
+        let state = app.state::<Mutex<AppState>>();
+        let locked_state = state.lock().unwrap();
+        (
+            locked_state.github_key.clone(),
+            locked_state.website_repo.clone(),
+        )
+    };
+
+    if github_token.is_empty() {
+        log::error!("GitHub token is missing. User needs to authenticate.");
+        return Err(InvokeError::from(
+            "GitHub token is missing. Please authenticate first.",
+        ));
+    }
+    let Some(repo_path) = repo_path else {
+        return Err(InvokeError::from(
+            "The website repo has not been synced yet. Call sync_git_in_cache first.",
+        ));
+    };
+
+    let forge = app_forge(&app);
+    let author = forge
+        .commit_author(&github_token)
+        .await
+        .map_err(InvokeError::from)?;
+    let branch = forge.config().branch.clone();
+    let message = message.unwrap_or_else(|| "docs: update articles via editor".to_string());
+    log::info!(
+        "publish_local_changes: committing as {} <{}>",
+        author.name,
+        author.email
+    );
+
+    let progress = git_progress_emitter(&app);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let mut repo = git2::Repository::open(&repo_path)?;
+        substuff::publish_local_changes(
+            &mut repo,
+            &branch,
+            &author,
+            &message,
+            Some(&github_token),
+            &progress,
+        )
+    })
+    .await
+    .map_err(|e| InvokeError::from(format!("Publish task failed: {}", e)))?;
+
+    match result {
+        Ok(result) => {
+            log::info!(
+                "Published {} file(s) in commit {}",
+                result.files.len(),
+                result.commit_sha
+            );
+            Ok(result)
+        }
+        Err(e) => {
+            log::error!("Failed to publish local changes: {}", e);
+            Err(InvokeError::from(format!(
+                "Failed to publish local changes: {}",
+                e.message()
+            )))
+        }
+    }
+}
+
+/// Remaining GitHub REST budget as of the last API response, so the UI can
+/// warn before a bulk publish runs out of requests.
+#[tauri::command]
+fn get_github_rate_limit() -> Option<RateLimitBudget> {
+    github_rate_limit()
+}
+
+/// Repository, branch and paths the app is currently targeting.
+#[tauri::command]
+fn get_app_config(app: AppHandle) -> AppConfig {
+    app_config(&app)
+}
+
+/// Signs in with a personal access token instead of the device flow, for
+/// forges that don't offer one (Gitea) or users who prefer it. The token is
+/// checked against the forge before it is kept.
+#[tauri::command]
+async fn login_with_token(app: AppHandle, token: String) -> Result<CommitAuthor, InvokeError> {
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        return Err(InvokeError::from("The access token is empty."));
+    }
+    let author = app_forge(&app)
+        .commit_author(&token)
+        .await
+        .map_err(InvokeError::from)?;
+    log::info!("login_with_token: signed in as {}", author.name);
+
+    remember_token(&app, &token);
+    app.emit("auth-responded", &token)
+        .map_err(|e| InvokeError::from(format!("Failed to emit auth-responded: {}", e)))?;
+    Ok(author)
+}
+
+/// Token restored from the keyring at startup, if any. It's validated in the
+/// background and `auth-expired` is emitted if the forge rejects it.
+#[tauri::command]
+fn restore_session(app: AppHandle) -> Option<String> {
+    let state = app.state::<Mutex<AppState>>();
+    let token = state.lock().unwrap().github_key.clone();
+    (!token.is_empty()).then_some(token)
+}
+
+#[derive(serde::Serialize, Clone)]
+struct LogoutResult {
+    /// False when the forge can't revoke the token for us.
+    revoked: bool,
+    /// Where the user can revoke the app's access themselves.
+    token_settings_url: String,
+}
+
+/// Wipes the token from memory and the keyring, then tries to revoke it.
+#[tauri::command]
+async fn logout(app: AppHandle) -> LogoutResult {
+    let forge = app_forge(&app);
+    let token = forget_token(&app);
+    let revoked = if token.is_empty() {
+        false
+    } else {
+        match forge.revoke_token(&token).await {
+            Ok(revoked) => revoked,
+            Err(e) => {
+                log::error!("logout: failed to revoke the token: {}", e);
+                false
+            }
+        }
+    };
+    log::info!("logout: token wiped, revoked on the forge: {}", revoked);
+    LogoutResult {
+        revoked,
+        token_settings_url: forge.token_settings_url(),
+    }
+}
+
+/// Who the current token belongs to and whether it can push to the
+/// configured repo, so missing access shows up right after login instead of
+/// as a 403 on the first upload.
+#[tauri::command]
+async fn whoami(app: AppHandle) -> Result<WhoAmI, InvokeError> {
+    let github_token = {
+        let state = app.state::<Mutex<AppState>>();
+        let locked_state = state.lock().unwrap();
+        locked_state.github_key.clone()
+    };
+    if github_token.is_empty() {
+        return Err(InvokeError::from(
+            "GitHub token is missing. Please authenticate first.",
+        ));
+    }
+    app_forge(&app)
+        .whoami(&github_token)
+        .await
+        .map_err(InvokeError::from)
+}
+
+fn require_token(app: &AppHandle) -> Result<String, InvokeError> {
+    let state = app.state::<Mutex<AppState>>();
+    let github_token = state.lock().unwrap().github_key.clone();
+    if github_token.is_empty() {
+        return Err(InvokeError::from(
+            "GitHub token is missing. Please authenticate first.",
+        ));
+    }
+    Ok(github_token)
+}
+
+fn require_website_repo(app: &AppHandle) -> Result<PathBuf, InvokeError> {
+    let state = app.state::<Mutex<AppState>>();
+    let repo_path = state.lock().unwrap().website_repo.clone();
+    repo_path.ok_or_else(|| {
+        InvokeError::from("The website repo has not been synced yet. Call sync_git_in_cache first.")
+    })
+}
+
+/// Summaries of every article on the configured branch, for the article table.
+#[tauri::command]
+async fn list_remote_articles(app: AppHandle) -> Result<RemoteArticles, InvokeError> {
+    let github_token = require_token(&app)?;
+    let forge = app_forge(&app);
+    substuff::list_remote_articles(forge.as_ref(), &github_token)
+        .await
+        .map_err(InvokeError::from)
+}
+
+/// Upgrades every article on the configured branch to the current
+/// `schemaVersion` in one commit.
+#[tauri::command]
+async fn migrate_all_articles(app: AppHandle) -> Result<MigrationSummary, InvokeError> {
+    let github_token = require_token(&app)?;
+    let forge = app_forge(&app);
+    substuff::migrate_remote_articles(forge.as_ref(), &github_token)
+        .await
+        .map_err(InvokeError::from)
+}
+
+/// One article from the configured branch, with the `sha` to upload it back with.
+#[tauri::command]
+async fn get_remote_article(app: AppHandle, path: String) -> Result<LoadedArticle, InvokeError> {
+    let github_token = require_token(&app)?;
+    let forge = app_forge(&app);
+    fetch_remote_article(forge.as_ref(), &github_token, &path)
+        .await
+        .map_err(InvokeError::from)
+}
+
+/// Summaries of the articles in the synced clone, no network needed.
+#[tauri::command]
+async fn list_local_articles(app: AppHandle) -> Result<LocalArticlesSummary, InvokeError> {
+    let repo_path = require_website_repo(&app)?;
+    let articles_dir = app_config(&app).articles_dir;
+    tauri::async_runtime::spawn_blocking(move || find_local_articles(&repo_path, &articles_dir))
+        .await
+        .map_err(|e| InvokeError::from(format!("Listing task failed: {}", e)))?
+        .map_err(|e| InvokeError::from(format!("{:#}", e)))
+}
+
+#[tauri::command]
+async fn get_local_article(app: AppHandle, path: String) -> Result<LoadedArticle, InvokeError> {
+    let repo_path = require_website_repo(&app)?;
+    let articles_dir = app_config(&app).articles_dir;
+    read_local_article(&repo_path, &articles_dir, &path)
+        .map_err(|e| InvokeError::from(format!("{:#}", e)))
+}
+
+/// Deletes an article from the configured branch. `force` skips the check
+/// that no other article links to it.
+#[tauri::command]
+async fn delete_article(
+    app: AppHandle,
+    path: String,
+    sha: String,
+    force: Option<bool>,
+) -> Result<ArticleChange, InvokeError> {
+    let github_token = require_token(&app)?;
+    let forge = app_forge(&app);
+    substuff::delete_article(
+        forge.as_ref(),
+        &github_token,
+        &path,
+        &sha,
+        force.unwrap_or(false),
+    )
+    .await
+    .map_err(InvokeError::from)
+}
+
+/// Renames an article, `new_file_name` is relative to the articles dir.
+#[tauri::command]
+async fn rename_article(
+    app: AppHandle,
+    path: String,
+    new_file_name: String,
+    sha: String,
+    force: Option<bool>,
+) -> Result<ArticleChange, InvokeError> {
+    let github_token = require_token(&app)?;
+    let forge = app_forge(&app);
+    substuff::rename_article(
+        forge.as_ref(),
+        &github_token,
+        &path,
+        &new_file_name,
+        &sha,
+        force.unwrap_or(false),
+    )
+    .await
+    .map_err(InvokeError::from)
+}
+
+/// Reverts what `delete_article` or `rename_article` returned.
+#[tauri::command]
+async fn undo_article_change(app: AppHandle, change: ArticleChange) -> Result<String, InvokeError> {
+    let github_token = require_token(&app)?;
+    let forge = app_forge(&app);
+    revert_article_change(forge.as_ref(), &github_token, &change)
+        .await
+        .map_err(InvokeError::from)
+}
@@ -0,0 +1,519 @@
+use crate::forge::Forge;
+use crate::{
+    article_from_toml_str, upgrade_article_toml, Article, GitHubEntryKind, GitHubError,
+    GitHubFileUpload, CURRENT_SCHEMA_VERSION,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+use ts_rs::TS;
+
+/// How many articles are downloaded at once when listing the remote repo.
+const CONCURRENT_FETCHES: usize = 8;
+
+/// The columns of the article table, without the body.
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct ArticleSummary {
+    /// Relative to the repo root, `/` separated.
+    pub path: String,
+    /// Blob SHA of the file, pass it back as `sha` when uploading changes.
+    /// Empty for a local file that isn't committed yet.
+    pub sha: String,
+    pub title: String,
+    pub category: String,
+    pub authors: Vec<String>,
+    #[serde(with = "crate::timestamps::timestamp")]
+    #[ts(as = "String")]
+    pub published_at: DateTime<Utc>,
+}
+
+impl ArticleSummary {
+    pub fn new(path: String, sha: String, article: &Article) -> Self {
+        ArticleSummary {
+            path,
+            sha,
+            title: article.title.clone(),
+            category: article.category.clone(),
+            authors: article
+                .authors
+                .iter()
+                .map(|author| author.name.clone())
+                .collect(),
+            published_at: article.published_at,
+        }
+    }
+}
+
+/// One article file, parsed, with the blob SHA it was read at.
+#[derive(Serialize, Debug, TS)]
+pub struct LoadedArticle {
+    pub path: String,
+    /// `path` relative to the articles dir, the `file_name` uploads take.
+    pub file_name: String,
+    /// `None` for a local file that isn't committed yet, it uploads as a new
+    /// article.
+    pub sha: Option<String>,
+    pub article: Article,
+}
+
+/// Every article on the configured branch.
+#[derive(Serialize, Debug, Default)]
+pub struct RemoteArticles {
+    pub articles: Vec<ArticleSummary>,
+    pub unreadable: Vec<String>, // TOML files that did not parse into an Article
+}
+
+fn is_article_path(path: &str) -> bool {
+    path.ends_with(".toml")
+}
+
+fn file_name_in(articles_dir: &str, path_in_repo: &str) -> String {
+    path_in_repo
+        .strip_prefix(articles_dir)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(path_in_repo)
+        .to_string()
+}
+
+/// Every article file below `articles_dir` on the forge.
+async fn remote_article_paths(forge: &dyn Forge, token: &str) -> Result<Vec<String>, GitHubError> {
+    let paths = forge
+        .list_tree(token, &forge.config().articles_dir)
+        .await?
+        .into_iter()
+        .filter(|entry| entry.r#type == GitHubEntryKind::File && is_article_path(&entry.path))
+        .map(|entry| entry.path)
+        .collect();
+    Ok(paths)
+}
+
+/// Lists the articles below `articles_dir` on the forge, downloading each one
+/// for its summary.
+pub async fn list_remote_articles(
+    forge: &dyn Forge,
+    token: &str,
+) -> Result<RemoteArticles, GitHubError> {
+    let articles_dir = &forge.config().articles_dir;
+    let paths = remote_article_paths(forge, token).await?;
+
+    let fetched: Vec<_> = stream::iter(paths)
+        .map(|path| async move {
+            let loaded = fetch_remote_article(forge, token, &path).await;
+            (path, loaded)
+        })
+        .buffered(CONCURRENT_FETCHES)
+        .collect()
+        .await;
+
+    let mut listing = RemoteArticles::default();
+    for (path, loaded) in fetched {
+        match loaded {
+            Ok(loaded) => listing.articles.push(ArticleSummary::new(
+                loaded.path,
+                loaded.sha.unwrap_or_default(),
+                &loaded.article,
+            )),
+            Err(e) => {
+                log::warn!("Skipping unreadable article {}: {}", path, e);
+                listing.unreadable.push(path);
+            }
+        }
+    }
+    log::info!(
+        "Found {} remote articles ({} unreadable) in {}",
+        listing.articles.len(),
+        listing.unreadable.len(),
+        articles_dir
+    );
+    Ok(listing)
+}
+
+/// Downloads and parses one article from the configured branch. A file that
+/// isn't a valid article comes back as `GitHubError::InvalidResponse`.
+pub async fn fetch_remote_article(
+    forge: &dyn Forge,
+    token: &str,
+    path_in_repo: &str,
+) -> Result<LoadedArticle, GitHubError> {
+    let remote =
+        forge
+            .get_file(token, path_in_repo)
+            .await?
+            .ok_or_else(|| GitHubError::NotFound {
+                path: path_in_repo.to_string(),
+                message: format!("{} does not exist on the branch", path_in_repo),
+                documentation_url: None,
+            })?;
+    let article =
+        article_from_toml_str(&remote.content).map_err(|e| GitHubError::InvalidResponse {
+            path: path_in_repo.to_string(),
+            message: format!("{:#}", e),
+        })?;
+    Ok(LoadedArticle {
+        path: path_in_repo.to_string(),
+        file_name: file_name_in(&forge.config().articles_dir, path_in_repo),
+        sha: Some(remote.sha),
+        article,
+    })
+}
+
+/// The blob SHA `path_in_repo` has in HEAD of the clone, what the forge had
+/// at the last sync, so an article edited locally still uploads with
+/// conflict detection. `None` if the file isn't committed.
+pub fn local_blob_sha(repo: &git2::Repository, path_in_repo: &Path) -> Result<Option<String>> {
+    let tree = repo
+        .head()
+        .and_then(|head| head.peel_to_tree())
+        .context("The local clone has no HEAD commit")?;
+    match tree.get_path(path_in_repo) {
+        Ok(entry) => Ok(Some(entry.id().to_string())),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Could not look up {:?} in HEAD", path_in_repo)),
+    }
+}
+
+/// Reads and parses one article from the local clone.
+pub fn read_local_article(
+    repo_path: &Path,
+    articles_dir: &str,
+    path_in_repo: &str,
+) -> Result<LoadedArticle> {
+    let relative = Path::new(path_in_repo);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        anyhow::bail!("{:?} is not a path inside the repo", path_in_repo);
+    }
+    let file_path = repo_path.join(path_in_repo);
+    let article = crate::get_article_from_toml_file(&file_path)?;
+    let repo = git2::Repository::open(repo_path)
+        .with_context(|| format!("{:?} is not a git repo", repo_path))?;
+    Ok(LoadedArticle {
+        path: path_in_repo.to_string(),
+        file_name: file_name_in(articles_dir, path_in_repo),
+        sha: local_blob_sha(&repo, relative)?,
+        article,
+    })
+}
+
+/// What `delete_article` or `rename_article` did, enough to undo it.
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ArticleChange {
+    Deleted {
+        path: String,
+        /// Blob SHA of the deleted file, its content can still be fetched.
+        sha: String,
+        commit_sha: String,
+        message: String,
+    },
+    Renamed {
+        from_path: String,
+        to_path: String,
+        sha: String,
+        commit_sha: String,
+        message: String,
+    },
+}
+
+/// The slug the website serves an article under, its file name without
+/// `.toml`.
+pub fn article_slug(path_in_repo: &str) -> &str {
+    let file_name = path_in_repo.rsplit('/').next().unwrap_or(path_in_repo);
+    file_name.strip_suffix(".toml").unwrap_or(file_name)
+}
+
+/// Whether `content` has a link ending in `/<slug>`, e.g. `/articles/<slug>`
+/// or `/articles/<slug>#questions`. `/images/<slug>.png` is a file, not a
+/// link to the article.
+fn references_slug(content: &str, slug: &str) -> bool {
+    let is_slug_char = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    content.match_indices(slug).any(|(start, _)| {
+        let before = content[..start].chars().next_back();
+        let mut after = content[start + slug.len()..].chars();
+        let ends_slug = match after.next() {
+            None => true,
+            Some('.') => !after.next().is_some_and(char::is_alphanumeric),
+            Some(c) => !is_slug_char(c),
+        };
+        before == Some('/') && ends_slug
+    })
+}
+
+/// Paths of the other articles that link to `slug`.
+pub async fn find_slug_references(
+    forge: &dyn Forge,
+    token: &str,
+    slug: &str,
+    own_path: &str,
+) -> Result<Vec<String>, GitHubError> {
+    let paths: Vec<String> = forge
+        .list_tree(token, &forge.config().articles_dir)
+        .await?
+        .into_iter()
+        .filter(|entry| {
+            entry.r#type == GitHubEntryKind::File
+                && is_article_path(&entry.path)
+                && entry.path != own_path
+        })
+        .map(|entry| entry.path)
+        .collect();
+
+    let fetched: Vec<_> = stream::iter(paths)
+        .map(|path| async move {
+            let remote = forge.get_file(token, &path).await;
+            (path, remote)
+        })
+        .buffered(CONCURRENT_FETCHES)
+        .collect()
+        .await;
+
+    let mut referenced_by = Vec::new();
+    for (path, remote) in fetched {
+        if remote?.is_some_and(|remote| references_slug(&remote.content, slug)) {
+            referenced_by.push(path);
+        }
+    }
+    Ok(referenced_by)
+}
+
+async fn ensure_slug_unreferenced(
+    forge: &dyn Forge,
+    token: &str,
+    path_in_repo: &str,
+) -> Result<(), GitHubError> {
+    let slug = article_slug(path_in_repo);
+    let referenced_by = find_slug_references(forge, token, slug, path_in_repo).await?;
+    if referenced_by.is_empty() {
+        return Ok(());
+    }
+    log::warn!("{} is still linked from {:?}", path_in_repo, referenced_by);
+    Err(GitHubError::StillReferenced {
+        path: path_in_repo.to_string(),
+        slug: slug.to_string(),
+        referenced_by,
+    })
+}
+
+/// Deletes an article from the configured branch. Unless `force` is set,
+/// refuses with `GitHubError::StillReferenced` while other articles link to
+/// it.
+pub async fn delete_article(
+    forge: &dyn Forge,
+    token: &str,
+    path_in_repo: &str,
+    expected_sha: &str,
+    force: bool,
+) -> Result<ArticleChange, GitHubError> {
+    if !force {
+        ensure_slug_unreferenced(forge, token, path_in_repo).await?;
+    }
+    let message = format!("docs: delete article '{}' via editor", path_in_repo);
+    let commit_sha = forge
+        .delete_file(token, path_in_repo, &message, expected_sha)
+        .await?;
+    Ok(ArticleChange::Deleted {
+        path: path_in_repo.to_string(),
+        sha: expected_sha.to_string(),
+        commit_sha,
+        message,
+    })
+}
+
+/// Renames an article to `new_file_name` (relative to the articles dir) in a
+/// single commit, which changes its slug. The same reference check as
+/// `delete_article` applies to the old slug.
+pub async fn rename_article(
+    forge: &dyn Forge,
+    token: &str,
+    path_in_repo: &str,
+    new_file_name: &str,
+    expected_sha: &str,
+    force: bool,
+) -> Result<ArticleChange, GitHubError> {
+    let valid_name = is_article_path(new_file_name)
+        && new_file_name
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..");
+    if !valid_name {
+        return Err(GitHubError::InvalidRequest {
+            message: format!(
+                "{:?} is not a valid article file name, it has to end in .toml",
+                new_file_name
+            ),
+        });
+    }
+    let to_path = forge.config().article_path(new_file_name);
+    if to_path == path_in_repo {
+        return Err(GitHubError::InvalidRequest {
+            message: format!("{} already has that name", path_in_repo),
+        });
+    }
+    if !force {
+        ensure_slug_unreferenced(forge, token, path_in_repo).await?;
+    }
+    let message = format!(
+        "docs: rename article '{}' to '{}' via editor",
+        path_in_repo, to_path
+    );
+    let commit_sha = forge
+        .move_file(token, path_in_repo, &to_path, &message, expected_sha)
+        .await?;
+    Ok(ArticleChange::Renamed {
+        from_path: path_in_repo.to_string(),
+        to_path,
+        sha: expected_sha.to_string(),
+        commit_sha,
+        message,
+    })
+}
+
+/// Undoes a delete or rename with a new commit, the way `git revert` would.
+/// Conflicts if the path was changed again after `change`.
+pub async fn revert_article_change(
+    forge: &dyn Forge,
+    token: &str,
+    change: &ArticleChange,
+) -> Result<String, GitHubError> {
+    match change {
+        ArticleChange::Deleted {
+            path,
+            sha,
+            commit_sha,
+            message,
+        } => {
+            let content = forge.get_blob(token, sha).await?;
+            forge
+                .put_file(
+                    token,
+                    path,
+                    &revert_message(message, commit_sha),
+                    &content,
+                    None,
+                )
+                .await
+        }
+        ArticleChange::Renamed {
+            from_path,
+            to_path,
+            sha,
+            commit_sha,
+            message,
+        } => {
+            forge
+                .move_file(
+                    token,
+                    to_path,
+                    from_path,
+                    &revert_message(message, commit_sha),
+                    sha,
+                )
+                .await
+        }
+    }
+}
+
+fn revert_message(message: &str, commit_sha: &str) -> String {
+    format!(
+        "Revert \"{}\"\n\nThis reverts commit {}.",
+        message, commit_sha
+    )
+}
+
+/// What `migrate_remote_articles` did.
+#[derive(Serialize, Debug, Default, TS)]
+pub struct MigrationSummary {
+    /// Articles rewritten at `CURRENT_SCHEMA_VERSION`.
+    pub migrated: Vec<String>,
+    pub up_to_date: usize,
+    /// Files that aren't articles even after migrating, left as they are.
+    pub unreadable: Vec<String>,
+    /// The forge's message for the commit, `None` if nothing needed migrating.
+    pub committed: Option<String>,
+}
+
+/// Migrates every article on the configured branch that is older than
+/// `CURRENT_SCHEMA_VERSION`, committing them all at once.
+///
+/// Everything is read at one commit of the branch and committed on top of
+/// it, an article edited in the meantime fails the whole migration with
+/// `GitHubError::Conflict` instead of being overwritten.
+pub async fn migrate_remote_articles(
+    forge: &dyn Forge,
+    token: &str,
+) -> Result<MigrationSummary, GitHubError> {
+    let branch = &forge.config().branch;
+    let head = forge.branch_head(token, branch).await?;
+    let paths = remote_article_paths(forge, token).await?;
+    let head_ref = &head;
+    let fetched: Vec<_> = stream::iter(paths)
+        .map(|path| async move {
+            let remote = forge.get_file_at(token, &path, head_ref).await;
+            (path, remote)
+        })
+        .buffered(CONCURRENT_FETCHES)
+        .collect()
+        .await;
+
+    let mut summary = MigrationSummary::default();
+    let mut files = Vec::new();
+    for (path, remote) in fetched {
+        // Added after `head`, the listing is of the branch as it is now
+        let Some(remote) = remote? else { continue };
+        match upgrade_article_toml(&remote.content) {
+            Ok(Some(migrated)) => {
+                files.push(GitHubFileUpload::text(path.clone(), &migrated));
+                summary.migrated.push(path);
+            }
+            Ok(None) => summary.up_to_date += 1,
+            Err(e) => {
+                log::warn!("Not migrating {}: {:#}", path, e);
+                summary.unreadable.push(path);
+            }
+        }
+    }
+
+    if !files.is_empty() {
+        let message = format!(
+            "docs: migrate {} article(s) to schema version {}",
+            files.len(),
+            CURRENT_SCHEMA_VERSION
+        );
+        let committed = forge
+            .commit_files_on(token, branch, &head, &message, &files)
+            .await?;
+        summary.committed = Some(committed);
+    }
+    log::info!(
+        "Migrated {} articles, {} already current, {} unreadable",
+        summary.migrated.len(),
+        summary.up_to_date,
+        summary.unreadable.len()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::references_slug;
+
+    #[test]
+    fn references_slug_matches_links_to_the_article() {
+        assert!(references_slug("[see](/articles/water)", "water"));
+        assert!(references_slug(
+            "[quiz](/articles/water#questions)",
+            "water"
+        ));
+        assert!(references_slug("See /articles/water.", "water"));
+    }
+
+    #[test]
+    fn references_slug_ignores_other_slugs_and_files() {
+        assert!(!references_slug("[see](/articles/water-2)", "water"));
+        assert!(!references_slug("![](/images/water.png)", "water"));
+        assert!(!references_slug("saltwater and water", "water"));
+    }
+}
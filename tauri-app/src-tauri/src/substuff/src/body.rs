@@ -0,0 +1,278 @@
+use crate::math::{render_chem_in_text, render_html_math, render_markdown_math, TextPiece};
+use crate::validation::Diagnostic;
+use ammonia::Builder;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use ts_rs::TS;
+
+/// What `Article.body` is written in. Articles from before Markdown support
+/// have no `bodyFormat` and are HTML.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyFormat {
+    #[default]
+    Html,
+    Markdown,
+}
+
+/// Markup the sanitizer always drops, for the editor to warn about.
+const DROPPED_TAGS: [&str; 7] = [
+    "<script", "<style", "<iframe", "<object", "<embed", "<form", "<link",
+];
+
+/// MathML elements and attributes the formula renderer produces.
+const MATHML_TAGS: [&str; 19] = [
+    "math",
+    "mrow",
+    "mi",
+    "mn",
+    "mo",
+    "mtext",
+    "mspace",
+    "msub",
+    "msup",
+    "msubsup",
+    "mfrac",
+    "msqrt",
+    "mroot",
+    "mover",
+    "munder",
+    "munderover",
+    "mmultiscripts",
+    "mprescripts",
+    "none",
+];
+const MATHML_ATTRIBUTES: [(&str, &str); 6] = [
+    ("math", "display"),
+    ("mi", "mathvariant"),
+    ("mo", "stretchy"),
+    ("mover", "accent"),
+    ("mspace", "width"),
+    ("munderover", "accent"),
+];
+
+/// Put in front of every `id` in a body, so an `id="location"` or
+/// `id="__proto__"` can't shadow the page's globals (DOM clobbering).
+/// Same-page links get it too, footnote references still find their notes.
+const ID_PREFIX: &str = "user-content-";
+
+/// CommonMark plus the extensions articles use, footnotes for citations and
+/// tables for data, with `$...$` math and `\ce{...}` rendered to MathML.
+pub fn render_markdown(source: &str) -> String {
+    markdown_to_html(source, &mut Vec::new())
+}
+
+fn markdown_to_html(source: &str, issues: &mut Vec<Diagnostic>) -> String {
+    let options = Options::ENABLE_FOOTNOTES | Options::ENABLE_TABLES | Options::ENABLE_MATH;
+    // The parser splits text at `[`, `*` and the like, join it back up so a
+    // `\ce{A ->[heat] B}` arrives whole
+    let mut events: Vec<Event> = Vec::new();
+    for event in Parser::new_ext(source, options) {
+        match (events.last_mut(), event) {
+            (Some(Event::Text(text)), Event::Text(more)) => {
+                *text = CowStr::from(format!("{}{}", text, more));
+            }
+            (_, event) => events.push(event),
+        }
+    }
+
+    let mut rendered_events = Vec::with_capacity(events.len());
+    for event in events {
+        match event {
+            Event::InlineMath(source) => rendered_events.push(math_event(source, false, issues)),
+            Event::DisplayMath(source) => rendered_events.push(math_event(source, true, issues)),
+            Event::Text(text) if text.contains("\\ce{") => {
+                for piece in render_chem_in_text(&text, issues) {
+                    rendered_events.push(match piece {
+                        TextPiece::Text(text) => Event::Text(text.to_string().into()),
+                        TextPiece::Html(mathml) => Event::InlineHtml(mathml.into()),
+                    });
+                }
+            }
+            event => rendered_events.push(event),
+        }
+    }
+
+    let mut rendered = String::with_capacity(source.len() * 3 / 2);
+    html::push_html(&mut rendered, rendered_events.into_iter());
+    rendered
+}
+
+/// Math from the Markdown parser as MathML, or as its source in a code span
+/// when it doesn't parse.
+fn math_event<'a>(source: CowStr<'a>, display: bool, issues: &mut Vec<Diagnostic>) -> Event<'a> {
+    match render_markdown_math(&source, display, issues) {
+        Some(mathml) => Event::InlineHtml(mathml.into()),
+        None => Event::Code(source),
+    }
+}
+
+/// The allowlist bodies are cleaned with: ammonia's defaults (formatting,
+/// links, images, figures, tables; no scripts, styles or event handlers)
+/// plus what `render_markdown` needs for footnote anchors and column
+/// alignment, with ids moved under `ID_PREFIX`.
+fn sanitizer() -> Builder<'static> {
+    let mut builder = Builder::default();
+    builder
+        .add_generic_attributes(["id", "class"])
+        .id_prefix(Some(ID_PREFIX))
+        .attribute_filter(|_, attribute, value| match value.strip_prefix('#') {
+            Some(fragment) if attribute == "href" && !fragment.starts_with(ID_PREFIX) => {
+                Some(Cow::Owned(format!("#{}{}", ID_PREFIX, fragment)))
+            }
+            _ => Some(Cow::Borrowed(value)),
+        })
+        .add_tag_attributes("th", ["style"])
+        .add_tag_attributes("td", ["style"])
+        .filter_style_properties(HashSet::from(["text-align"]))
+        .add_tags(MATHML_TAGS);
+    for (tag, attribute) in MATHML_ATTRIBUTES {
+        builder.add_tag_attributes(tag, [attribute]);
+    }
+    builder
+}
+
+/// Strips everything outside the allowlist from `html`, e.g. `<script>`,
+/// `onclick=` and `javascript:` links.
+pub fn sanitize_html(html: &str) -> String {
+    sanitizer().clean(html).to_string()
+}
+
+fn body_to_html(body: &str, format: BodyFormat, issues: &mut Vec<Diagnostic>) -> String {
+    match format {
+        BodyFormat::Html => render_html_math(body, issues),
+        BodyFormat::Markdown => markdown_to_html(body, issues),
+    }
+}
+
+/// The HTML the website shows for `body`, sanitized, with formulas as MathML.
+pub fn render_body(body: &str, format: BodyFormat) -> String {
+    sanitize_html(&body_to_html(body, format, &mut Vec::new()))
+}
+
+/// Formulas in `body` that don't parse, and reactions that don't balance.
+pub(crate) fn check_body_formulas(body: &str, format: BodyFormat) -> Vec<Diagnostic> {
+    let mut issues = Vec::new();
+    body_to_html(body, format, &mut issues);
+    issues
+}
+
+/// Whether `html` has markup `sanitize_html` is going to drop. A quick scan
+/// for the editor, the sanitizer doesn't rely on it.
+pub fn has_unsafe_markup(html: &str) -> bool {
+    let lower = html.to_lowercase();
+    DROPPED_TAGS.iter().any(|tag| lower.contains(tag))
+        || lower.contains("javascript:")
+        || lower.match_indices(" on").any(|(i, _)| {
+            let inside_tag = lower[..i].rfind('<') > lower[..i].rfind('>');
+            let after_name = lower[i + 3..].trim_start_matches(|c: char| c.is_ascii_alphabetic());
+            inside_tag
+                && after_name.len() < lower.len() - i - 3
+                && after_name.trim_start().starts_with('=')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizer_strips_scripts_handlers_and_javascript_links() {
+        assert_eq!(
+            sanitize_html("<p>Hi<script>alert(1)</script></p>"),
+            "<p>Hi</p>"
+        );
+        assert_eq!(
+            sanitize_html(r#"<img src="a.png" onerror="alert(1)">"#),
+            r#"<img src="a.png">"#
+        );
+        assert_eq!(
+            sanitize_html(r#"<a href="javascript:alert(1)">x</a>"#),
+            r#"<a rel="noopener noreferrer">x</a>"#
+        );
+        assert_eq!(
+            sanitize_html(r#"<p style="color: red" onclick="x()">x</p>"#),
+            "<p>x</p>"
+        );
+    }
+
+    #[test]
+    fn sanitizer_keeps_mathml() {
+        let mathml = concat!(
+            r#"<math display="block"><mrow><msup><mi mathvariant="normal">x</mi><mn>2</mn></msup>"#,
+            r#"<mover accent="true"><mo stretchy="false">→</mo><mtext>heat</mtext></mover>"#,
+            r#"<mmultiscripts><mi>C</mi><mprescripts></mprescripts><none></none><mn>14</mn></mmultiscripts>"#,
+            r#"<mspace width="0.2em"></mspace></mrow></math>"#,
+        );
+        assert_eq!(sanitize_html(mathml), mathml);
+        assert_eq!(
+            sanitize_html(r#"<math><mi href="javascript:x()" onclick="x()">x</mi></math>"#),
+            "<math><mi>x</mi></math>"
+        );
+    }
+
+    #[test]
+    fn markdown_keeps_footnotes_and_tables() {
+        let html = render_body(
+            "Water[^1].\n\n| A | B |\n|:--|--:|\n| 1 | 2 |\n\n[^1]: Mostly.\n",
+            BodyFormat::Markdown,
+        );
+        assert_eq!(
+            html,
+            concat!(
+                "<p>Water<sup class=\"footnote-reference\"><a href=\"#user-content-1\" rel=\"noopener noreferrer\">1</a></sup>.</p>\n",
+                "<table><thead><tr><th style=\"text-align:left\">A</th><th style=\"text-align:right\">B</th></tr></thead><tbody>\n",
+                "<tr><td style=\"text-align:left\">1</td><td style=\"text-align:right\">2</td></tr>\n",
+                "</tbody></table>\n",
+                "<div class=\"footnote-definition\" id=\"user-content-1\"><sup class=\"footnote-definition-label\">1</sup>\n",
+                "<p>Mostly.</p>\n",
+                "</div>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn sanitizer_prefixes_ids_against_clobbering() {
+        assert_eq!(
+            sanitize_html(r#"<img id="location" src="a.png"><form id="__proto__"></form>"#),
+            r#"<img id="user-content-location" src="a.png">"#
+        );
+        assert_eq!(
+            sanitize_html(r##"<p id="__proto__"><a href="#__proto__">x</a></p>"##),
+            r##"<p id="user-content-__proto__"><a href="#user-content-__proto__" rel="noopener noreferrer">x</a></p>"##
+        );
+        assert_eq!(
+            sanitize_html(r#"<a href="https://example.com/#top">x</a>"#),
+            r#"<a href="https://example.com/#top" rel="noopener noreferrer">x</a>"#
+        );
+    }
+
+    #[test]
+    fn has_unsafe_markup_finds_what_the_sanitizer_drops() {
+        for html in [
+            "<SCRIPT>x</SCRIPT>",
+            "<iframe src=x></iframe>",
+            "<style>p {}</style>",
+            r#"<a href="JavaScript:x()">x</a>"#,
+            r#"<img src=x onerror="x()">"#,
+            r#"<p onclick = "x()">x</p>"#,
+        ] {
+            assert!(has_unsafe_markup(html), "{}", html);
+        }
+    }
+
+    #[test]
+    fn has_unsafe_markup_ignores_lookalikes_in_text() {
+        for html in [
+            "<p>Turn the burner on = hot</p>",
+            "<p>Depends on the solvent</p>",
+            r#"<p class="one">x</p>"#,
+            "<p>a scripted reaction</p>",
+        ] {
+            assert!(!has_unsafe_markup(html), "{}", html);
+        }
+    }
+}
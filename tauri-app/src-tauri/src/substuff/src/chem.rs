@@ -0,0 +1,619 @@
+use std::collections::BTreeMap;
+
+/// Symbols `\ce{}` accepts as elements.
+const ELEMENTS: [&str; 118] = [
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+    "Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+    "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb",
+    "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl",
+    "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk",
+    "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh",
+    "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+/// Arrows `\ce{}` understands, longest first so `<=>>` isn't read as `<=>`.
+const ARROWS: [(&str, &str); 7] = [
+    ("<-->", "\u{21C4}"),
+    ("<=>>", "\u{21CC}"),
+    ("<<=>", "\u{21CC}"),
+    ("<=>", "\u{21CC}"),
+    ("<->", "\u{2194}"),
+    ("->", "\u{2192}"),
+    ("<-", "\u{2190}"),
+];
+
+/// A formula element or group and its count, as MathML.
+type Part = (String, Option<String>);
+
+/// Atoms per element, `None` when a count isn't a number (`C_nH_{2n}`).
+type Atoms = Option<BTreeMap<String, f64>>;
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn add_atoms(into: &mut Atoms, from: &Atoms, times: Option<f64>) {
+    match (into.as_mut(), from, times) {
+        (Some(into), Some(from), Some(times)) => {
+            for (element, count) in from {
+                *into.entry(element.clone()).or_default() += count * times;
+            }
+        }
+        _ => *into = None,
+    }
+}
+
+fn number_mathml(number: &str) -> String {
+    match number.split_once('/') {
+        Some((numerator, denominator)) => format!(
+            "<mfrac><mn>{}</mn><mn>{}</mn></mfrac>",
+            escape(numerator),
+            escape(denominator)
+        ),
+        None => format!("<mn>{}</mn>", escape(number)),
+    }
+}
+
+fn parse_number(number: &str) -> Option<f64> {
+    match number.split_once('/') {
+        Some((numerator, denominator)) => {
+            Some(numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?)
+        }
+        None => number.parse().ok(),
+    }
+}
+
+/// One formula unit, e.g. `H2O` or `[Cu(NH3)4]^2+`, with the MathML for it.
+struct Species {
+    mathml: String,
+    atoms: Atoms,
+    charge: f64,
+}
+
+pub(crate) struct Cursor {
+    chars: Vec<char>,
+    pub pos: usize,
+}
+
+impl Cursor {
+    pub fn new(text: &str) -> Self {
+        Cursor {
+            chars: text.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn rest(&self) -> String {
+        self.chars[self.pos..].iter().collect()
+    }
+
+    pub fn take_while(&mut self, mut keep: impl FnMut(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&mut keep) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// After an opening `{`, `(` or `[`, up to the matching `close`.
+    pub fn take_balanced(&mut self, open: char, close: char) -> Result<String, String> {
+        let start = self.pos;
+        let mut depth = 1;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(self.chars[start..self.pos - 1].iter().collect());
+                }
+            }
+        }
+        Err(format!("missing \"{}\"", close))
+    }
+
+    /// A sub- or superscript: `{...}` or a run of digits, letters or signs.
+    fn take_script(&mut self) -> Result<String, String> {
+        if self.peek() == Some('{') {
+            self.pos += 1;
+            return self.take_balanced('{', '}');
+        }
+        let script = self.take_while(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-');
+        if script.is_empty() {
+            return Err("\"^\" and \"_\" need something after them".to_string());
+        }
+        Ok(script)
+    }
+}
+
+/// `2+`, `-`, `3-` or `+2` as a number, `None` for e.g. oxidation states.
+fn parse_charge(charge: &str) -> Option<f64> {
+    let digits: String = charge.chars().filter(|c| c.is_ascii_digit()).collect();
+    let signs: String = charge.chars().filter(|c| !c.is_ascii_digit()).collect();
+    let size = if digits.is_empty() {
+        signs.len() as f64
+    } else if signs.len() == 1 {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    match signs.chars().next()? {
+        '+' if signs.chars().all(|c| c == '+') => Some(size),
+        '-' if signs.chars().all(|c| c == '-') => Some(-size),
+        _ => None,
+    }
+}
+
+fn charge_mathml(charge: &str) -> String {
+    let mut mathml = String::from("<mrow>");
+    for c in charge.chars() {
+        match c {
+            '0'..='9' => mathml.push_str(&format!("<mn>{}</mn>", c)),
+            '+' => mathml.push_str("<mo>+</mo>"),
+            '-' => mathml.push_str("<mo>\u{2212}</mo>"),
+            c => mathml.push_str(&format!(
+                "<mi mathvariant=\"normal\">{}</mi>",
+                escape(&c.to_string())
+            )),
+        }
+    }
+    mathml.push_str("</mrow>");
+    mathml
+}
+
+/// Parses elements, groups and counts up to the end of the cursor. Returns
+/// each part as (base, subscript) so the caller can attach a charge to
+/// the last one.
+fn parse_formula(cursor: &mut Cursor) -> Result<(Vec<Part>, Atoms, Option<String>), String> {
+    let mut parts: Vec<Part> = Vec::new();
+    let mut atoms: Atoms = Some(BTreeMap::new());
+    let mut charge = None;
+    // A hydrate like `CuSO4*5H2O` multiplies what follows the dot
+    let mut multiplier = Some(1.0);
+
+    while let Some(c) = cursor.peek() {
+        // Only a state can follow the charge, e.g. `Na+(aq)`
+        if charge.is_some() && c != '(' {
+            return Err(format!("unexpected {:?} after the charge", cursor.rest()));
+        }
+        let (base, part_atoms) = match c {
+            'A'..='Z' => {
+                cursor.pos += 1;
+                let mut symbol = c.to_string();
+                if let Some(next) = cursor.peek().filter(char::is_ascii_lowercase) {
+                    symbol.push(next);
+                    cursor.pos += 1;
+                }
+                if !ELEMENTS.contains(&symbol.as_str()) {
+                    return Err(format!("unknown element \"{}\"", symbol));
+                }
+                let base = format!("<mi mathvariant=\"normal\">{}</mi>", symbol);
+                (base, Some(BTreeMap::from([(symbol, 1.0)])))
+            }
+            '(' | '[' => {
+                let close = if c == '(' { ')' } else { ']' };
+                cursor.pos += 1;
+                let inner = cursor.take_balanced(c, close)?;
+                // `(aq)`, `(s)`, `(l)`, `(g)` and other states end the formula
+                if c == '(' && !inner.is_empty() && inner.chars().all(|c| c.is_ascii_lowercase()) {
+                    if !cursor.at_end() {
+                        return Err(format!("unexpected {:?} after ({})", cursor.rest(), inner));
+                    }
+                    parts.push((format!("<mtext>({})</mtext>", escape(&inner)), None));
+                    break;
+                }
+                if charge.is_some() {
+                    return Err(format!("unexpected ({}) after the charge", inner));
+                }
+                let mut inner_cursor = Cursor::new(&inner);
+                let (inner_parts, inner_atoms, inner_charge) = parse_formula(&mut inner_cursor)?;
+                if inner_charge.is_some() {
+                    return Err(format!("charge inside {}{}{}", c, inner, close));
+                }
+                let inner_mathml: String = inner_parts.into_iter().map(script_part).collect();
+                let base = format!(
+                    "<mrow><mo>{}</mo>{}<mo>{}</mo></mrow>",
+                    c, inner_mathml, close
+                );
+                (base, inner_atoms)
+            }
+            '*' | '.' | '\u{B7}' => {
+                cursor.pos += 1;
+                let coefficient = cursor.take_while(|c| c.is_ascii_digit());
+                parts.push(("<mo>\u{B7}</mo>".to_string(), None));
+                if coefficient.is_empty() {
+                    multiplier = Some(1.0);
+                } else {
+                    multiplier = coefficient.parse().ok();
+                    parts.push((number_mathml(&coefficient), None));
+                }
+                continue;
+            }
+            '^' => {
+                cursor.pos += 1;
+                charge = Some(cursor.take_script()?);
+                continue;
+            }
+            '+' | '-' if cursor.peek_at(1).is_none_or(|c| c == '(') => {
+                cursor.pos += 1;
+                charge = Some(c.to_string());
+                continue;
+            }
+            c => return Err(format!("unexpected {:?}", c)),
+        };
+
+        let count = if cursor.peek() == Some('_') {
+            cursor.pos += 1;
+            Some(cursor.take_script()?)
+        } else {
+            Some(cursor.take_while(|c| c.is_ascii_digit())).filter(|count| !count.is_empty())
+        };
+        let times = match &count {
+            Some(count) => count.parse::<f64>().ok(),
+            None => Some(1.0),
+        };
+        add_atoms(
+            &mut atoms,
+            &part_atoms,
+            times.zip(multiplier).map(|(a, b)| a * b),
+        );
+        let subscript = count.map(|count| match count.parse::<u32>() {
+            Ok(_) => format!("<mn>{}</mn>", count),
+            Err(_) => format!("<mi>{}</mi>", escape(&count)),
+        });
+        parts.push((base, subscript));
+    }
+    Ok((parts, atoms, charge))
+}
+
+fn script_part((base, subscript): Part) -> String {
+    match subscript {
+        Some(subscript) => format!("<msub>{}{}</msub>", base, subscript),
+        None => base,
+    }
+}
+
+/// One whitespace separated word of a `\ce{}`, e.g. `2H2O` or `SO4^2-(aq)`.
+fn parse_species(word: &str) -> Result<Species, String> {
+    let mut cursor = Cursor::new(word);
+    let mut mathml = String::from("<mrow>");
+
+    let coefficient = cursor.take_while(|c| c.is_ascii_digit() || c == '/' || c == '.');
+    if cursor.at_end() {
+        // A bare number, e.g. the 2 in `\ce{2 H2}`
+        mathml.push_str(&number_mathml(&coefficient));
+        mathml.push_str("</mrow>");
+        return Ok(Species {
+            mathml,
+            atoms: None,
+            charge: 0.0,
+        });
+    }
+    if !coefficient.is_empty() {
+        mathml.push_str(&number_mathml(&coefficient));
+    }
+    let times = if coefficient.is_empty() {
+        Some(1.0)
+    } else {
+        parse_number(&coefficient)
+    };
+
+    // Electrons, `e-` or `e^-`
+    if cursor.rest() == "e-" || cursor.rest() == "e^-" {
+        mathml.push_str("<msup><mi>e</mi><mo>\u{2212}</mo></msup></mrow>");
+        return Ok(Species {
+            mathml,
+            atoms: Some(BTreeMap::new()),
+            charge: -times.unwrap_or(1.0),
+        });
+    }
+
+    // Isotopes, `^{14}C` or `^{227}_{90}Th`
+    let mut mass = None;
+    let mut atomic_number = None;
+    if cursor.peek() == Some('^') {
+        cursor.pos += 1;
+        mass = Some(cursor.take_script()?);
+        if cursor.peek() == Some('_') {
+            cursor.pos += 1;
+            atomic_number = Some(cursor.take_script()?);
+        }
+    }
+
+    let (mut parts, atoms, charge) = parse_formula(&mut cursor)?;
+    if parts.is_empty() {
+        return Err(format!("{:?} has no formula", word));
+    }
+    if mass.is_some() {
+        let (base, subscript) = parts.remove(0);
+        let script = |value: Option<String>| match value {
+            Some(value) => format!("<mn>{}</mn>", escape(&value)),
+            None => "<none/>".to_string(),
+        };
+        parts.insert(
+            0,
+            (
+                format!(
+                    "<mmultiscripts>{}{}<none/><mprescripts/>{}{}</mmultiscripts>",
+                    base,
+                    subscript.unwrap_or_else(|| "<none/>".to_string()),
+                    script(atomic_number),
+                    script(mass),
+                ),
+                None,
+            ),
+        );
+    }
+
+    // The charge goes over the last element or group, next to its count
+    let state = match parts.last() {
+        Some((base, None)) if base.starts_with("<mtext>(") => parts.pop(),
+        _ => None,
+    };
+    let last = parts.pop();
+    mathml.extend(parts.into_iter().map(script_part));
+    if let Some((base, subscript)) = last {
+        mathml.push_str(&match (subscript, &charge) {
+            (Some(subscript), Some(charge)) => format!(
+                "<msubsup>{}{}{}</msubsup>",
+                base,
+                subscript,
+                charge_mathml(charge)
+            ),
+            (None, Some(charge)) => format!("<msup>{}{}</msup>", base, charge_mathml(charge)),
+            (subscript, None) => script_part((base, subscript)),
+        });
+    }
+    if let Some((state, _)) = state {
+        mathml.push_str(&state);
+    }
+    mathml.push_str("</mrow>");
+
+    let charge_value = match &charge {
+        Some(charge) => parse_charge(charge),
+        None => Some(0.0),
+    };
+    let mut species_atoms = Some(BTreeMap::new());
+    add_atoms(&mut species_atoms, &atoms, times);
+    Ok(Species {
+        mathml,
+        // Oxidation states like `^{II}` don't count towards the balance
+        atoms: species_atoms.filter(|_| charge_value.is_some()),
+        charge: charge_value.unwrap_or(0.0) * times.unwrap_or(1.0),
+    })
+}
+
+/// `\ce{}` content turned into MathML, and a note when it's a reaction that
+/// doesn't balance.
+pub(crate) struct Chem {
+    pub mathml: String,
+    pub unbalanced: Option<String>,
+}
+
+enum Side {
+    Left,
+    Right,
+}
+
+/// Renders mhchem-style `\ce{}` content: formulas with counts, charges,
+/// states, hydrates and isotopes, `+`, reaction arrows (with `[above]` and
+/// `[below]` text) and `^`/`v` for gas and precipitate.
+pub(crate) fn render_ce(source: &str) -> Result<Chem, String> {
+    let mut cursor = Cursor::new(source.trim());
+    if cursor.at_end() {
+        return Err("empty \\ce{}".to_string());
+    }
+    let mut mathml = String::from("<mrow>");
+    let mut side = Side::Left;
+    let mut arrows = 0;
+    // Atoms and charge per side, `None` once something can't be counted
+    let mut left: Atoms = Some(BTreeMap::new());
+    let mut right: Atoms = Some(BTreeMap::new());
+    let (mut left_charge, mut right_charge) = (0.0, 0.0);
+    let (mut left_species, mut right_species) = (0, 0);
+
+    loop {
+        cursor.take_while(char::is_whitespace);
+        if cursor.at_end() {
+            break;
+        }
+        let rest = cursor.rest();
+        if let Some((arrow, symbol)) = ARROWS.iter().find(|(arrow, _)| rest.starts_with(arrow)) {
+            cursor.pos += arrow.chars().count();
+            let mut labels = Vec::new();
+            while labels.len() < 2 && cursor.peek() == Some('[') {
+                cursor.pos += 1;
+                labels.push(cursor.take_balanced('[', ']')?);
+            }
+            let mo = format!("<mo stretchy=\"true\">{}</mo>", symbol);
+            let label = |text: &String| format!("<mtext>{}</mtext>", escape(text.trim()));
+            mathml.push_str(&match labels.as_slice() {
+                [] => mo,
+                [above] => format!("<mover>{}{}</mover>", mo, label(above)),
+                [above, below, ..] => format!(
+                    "<munderover>{}{}{}</munderover>",
+                    mo,
+                    label(below),
+                    label(above)
+                ),
+            });
+            side = Side::Right;
+            arrows += 1;
+            continue;
+        }
+
+        let mut depth = 0;
+        let word = cursor.take_while(|c| {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+            depth > 0 || !c.is_whitespace()
+        });
+        match word.as_str() {
+            "+" => mathml.push_str("<mo>+</mo>"),
+            "^" => mathml.push_str("<mo>\u{2191}</mo>"),
+            "v" => mathml.push_str("<mo>\u{2193}</mo>"),
+            _ => {
+                let species = parse_species(&word)?;
+                mathml.push_str(&species.mathml);
+                let (atoms, charge, count) = match side {
+                    Side::Left => (&mut left, &mut left_charge, &mut left_species),
+                    Side::Right => (&mut right, &mut right_charge, &mut right_species),
+                };
+                add_atoms(atoms, &species.atoms, Some(1.0));
+                *charge += species.charge;
+                *count += 1;
+            }
+        }
+    }
+    mathml.push_str("</mrow>");
+
+    let unbalanced = match (left, right) {
+        (Some(left), Some(right)) if arrows == 1 && left_species > 0 && right_species > 0 => {
+            describe_imbalance(&left, &right, left_charge, right_charge)
+        }
+        _ => None,
+    };
+    Ok(Chem { mathml, unbalanced })
+}
+
+fn describe_imbalance(
+    left: &BTreeMap<String, f64>,
+    right: &BTreeMap<String, f64>,
+    left_charge: f64,
+    right_charge: f64,
+) -> Option<String> {
+    let differs = |a: f64, b: f64| (a - b).abs() > 1e-9;
+    let mut elements: Vec<&String> = left.keys().chain(right.keys()).collect();
+    elements.sort();
+    elements.dedup();
+    let mut problems: Vec<String> = elements
+        .into_iter()
+        .filter_map(|element| {
+            let before = left.get(element).copied().unwrap_or(0.0);
+            let after = right.get(element).copied().unwrap_or(0.0);
+            differs(before, after).then(|| format!("{} {} \u{2192} {}", element, before, after))
+        })
+        .collect();
+    if differs(left_charge, right_charge) {
+        problems.push(format!("charge {} \u{2192} {}", left_charge, right_charge));
+    }
+    (!problems.is_empty()).then(|| problems.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ce(source: &str) -> (String, Option<String>) {
+        let chem = render_ce(source).unwrap();
+        (chem.mathml, chem.unbalanced)
+    }
+
+    #[test]
+    fn water() {
+        assert_eq!(
+            ce("H2O"),
+            (
+                concat!(
+                    "<mrow><mrow><msub><mi mathvariant=\"normal\">H</mi><mn>2</mn></msub>",
+                    "<mi mathvariant=\"normal\">O</mi></mrow></mrow>"
+                )
+                .to_string(),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn sulfate_has_count_and_charge() {
+        assert_eq!(
+            ce("SO4^2-"),
+            (
+                concat!(
+                    "<mrow><mrow><mi mathvariant=\"normal\">S</mi>",
+                    "<msubsup><mi mathvariant=\"normal\">O</mi><mn>4</mn>",
+                    "<mrow><mn>2</mn><mo>\u{2212}</mo></mrow></msubsup></mrow></mrow>"
+                )
+                .to_string(),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn arrow_with_one_label_goes_over_it() {
+        assert_eq!(
+            ce("CaCO3 ->[heat] CaO + CO2"),
+            (
+                concat!(
+                    "<mrow><mrow><mi mathvariant=\"normal\">Ca</mi><mi mathvariant=\"normal\">C</mi>",
+                    "<msub><mi mathvariant=\"normal\">O</mi><mn>3</mn></msub></mrow>",
+                    "<mover><mo stretchy=\"true\">\u{2192}</mo><mtext>heat</mtext></mover>",
+                    "<mrow><mi mathvariant=\"normal\">Ca</mi><mi mathvariant=\"normal\">O</mi></mrow>",
+                    "<mo>+</mo>",
+                    "<mrow><mi mathvariant=\"normal\">C</mi>",
+                    "<msub><mi mathvariant=\"normal\">O</mi><mn>2</mn></msub></mrow></mrow>"
+                )
+                .to_string(),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn arrow_with_two_labels_goes_between_them() {
+        assert_eq!(
+            ce("N2 + 3H2 <=>[Fe][400 C] 2NH3"),
+            (
+                concat!(
+                    "<mrow><mrow><msub><mi mathvariant=\"normal\">N</mi><mn>2</mn></msub></mrow>",
+                    "<mo>+</mo>",
+                    "<mrow><mn>3</mn><msub><mi mathvariant=\"normal\">H</mi><mn>2</mn></msub></mrow>",
+                    "<munderover><mo stretchy=\"true\">\u{21CC}</mo>",
+                    "<mtext>400 C</mtext><mtext>Fe</mtext></munderover>",
+                    "<mrow><mn>2</mn><mi mathvariant=\"normal\">N</mi>",
+                    "<msub><mi mathvariant=\"normal\">H</mi><mn>3</mn></msub></mrow></mrow>"
+                )
+                .to_string(),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn balance_counts_coefficients_and_charges() {
+        assert_eq!(ce("2H2 + O2 -> 2H2O").1, None);
+        assert_eq!(ce("H2 + O2 -> H2O").1.as_deref(), Some("O 2 \u{2192} 1"));
+        assert_eq!(ce("Fe^3+ + 3e- -> Fe").1.as_deref(), None,);
+        assert_eq!(ce("Na -> Na+").1.as_deref(), Some("charge 0 \u{2192} 1"));
+    }
+
+    #[test]
+    fn unknown_elements_and_empty_formulas_are_errors() {
+        assert_eq!(
+            render_ce("Xy2").err().as_deref(),
+            Some("unknown element \"Xy\"")
+        );
+        assert!(render_ce("  ").is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Name of the config file looked up in the app config dir.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Which kind of server hosts the website repo.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    /// github.com, or GitHub Enterprise with `api_base_url` set to
+    /// `https://<host>/api/v3`.
+    #[default]
+    GitHub,
+    /// Gitea or Forgejo, `api_base_url` is `https://<host>/api/v1`.
+    Gitea,
+}
+
+impl std::str::FromStr for ForgeKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "github" => Ok(ForgeKind::GitHub),
+            "gitea" | "forgejo" => Ok(ForgeKind::Gitea),
+            other => Err(anyhow::anyhow!(
+                "Unknown forge {:?}, expected \"github\" or \"gitea\"",
+                other
+            )),
+        }
+    }
+}
+
+/// Which repository the app reads and publishes articles to, and which forge
+/// (and endpoints) it talks to for that.
+///
+/// Loaded from `config.toml` in the app config dir, every field optional, and
+/// then overridden by `BEAKERS_*` environment variables so a staging fork or
+/// a test repo can be targeted without recompiling.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct AppConfig {
+    pub forge: ForgeKind,
+    pub owner: String,
+    pub repo: String,
+    pub branch: String,
+    pub articles_dir: String,
+    pub assets_dir: String,
+    pub client_id: String,
+    pub api_base_url: String,
+    /// Used for cloning and for the OAuth device flow endpoints.
+    pub web_base_url: String,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            forge: ForgeKind::GitHub,
+            owner: "thebeakers".to_string(),
+            repo: "TheBeakersWebsite".to_string(),
+            branch: "main".to_string(),
+            articles_dir: "src/articles".to_string(),
+            assets_dir: "static/images".to_string(),
+            client_id: "Ov23liELuI9vWwtgYC5f".to_string(),
+            api_base_url: "https://api.github.com".to_string(),
+            web_base_url: "https://github.com".to_string(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Reads `config.toml` from `config_dir` (defaults if it doesn't exist)
+    /// and applies environment overrides on top.
+    pub fn load(config_dir: &Path) -> Result<Self> {
+        let config_path = config_dir.join(CONFIG_FILE_NAME);
+        let mut config = if config_path.exists() {
+            let toml_content = fs::read_to_string(&config_path)
+                .with_context(|| format!("Failed to read the file: {:?}", config_path))?;
+            Self::from_toml_str(&toml_content)
+                .with_context(|| format!("Invalid config file: {:?}", config_path))?
+        } else {
+            log::info!("No config at {:?}, using defaults", config_path);
+            Self::default()
+        };
+        config.apply_env_overrides();
+        Ok(config)
+    }
+
+    pub fn from_toml_str(toml_content: &str) -> Result<Self> {
+        let config: AppConfig = toml::from_str(toml_content)
+            .context("Failed to parse the TOML content into an AppConfig struct")?;
+        Ok(config.normalized())
+    }
+
+    /// Overrides fields from `BEAKERS_FORGE`, `BEAKERS_OWNER`, `BEAKERS_REPO`,
+    /// `BEAKERS_BRANCH`, `BEAKERS_ARTICLES_DIR`, `BEAKERS_ASSETS_DIR`,
+    /// `BEAKERS_CLIENT_ID`, `BEAKERS_API_BASE_URL` and `BEAKERS_WEB_BASE_URL`
+    /// when they are set.
+    pub fn apply_env_overrides(&mut self) {
+        if let Ok(value) = env::var("BEAKERS_FORGE") {
+            match value.parse() {
+                Ok(forge) => self.forge = forge,
+                Err(e) => log::warn!("Ignoring BEAKERS_FORGE: {:#}", e),
+            }
+        }
+        let fields: [(&str, &mut String); 8] = [
+            ("BEAKERS_OWNER", &mut self.owner),
+            ("BEAKERS_REPO", &mut self.repo),
+            ("BEAKERS_BRANCH", &mut self.branch),
+            ("BEAKERS_ARTICLES_DIR", &mut self.articles_dir),
+            ("BEAKERS_ASSETS_DIR", &mut self.assets_dir),
+            ("BEAKERS_CLIENT_ID", &mut self.client_id),
+            ("BEAKERS_API_BASE_URL", &mut self.api_base_url),
+            ("BEAKERS_WEB_BASE_URL", &mut self.web_base_url),
+        ];
+        for (var, field) in fields {
+            if let Ok(value) = env::var(var) {
+                if !value.trim().is_empty() {
+                    log::info!("{} overrides config value {:?}", var, field);
+                    *field = value;
+                }
+            }
+        }
+        *self = self.clone().normalized();
+    }
+
+    /// Strips the slashes people tend to paste around URLs and paths, repo
+    /// paths are relative to the repo root.
+    fn normalized(mut self) -> Self {
+        for field in [
+            &mut self.articles_dir,
+            &mut self.assets_dir,
+            &mut self.api_base_url,
+            &mut self.web_base_url,
+        ] {
+            let trimmed = field.trim().trim_matches('/').to_string();
+            *field = trimmed;
+        }
+        self
+    }
+
+    pub fn clone_url(&self) -> String {
+        format!("{}/{}/{}.git", self.web_base_url, self.owner, self.repo)
+    }
+
+    /// `{api_base_url}/repos/{owner}/{repo}`, the prefix of every repo endpoint.
+    pub fn repo_api_url(&self) -> String {
+        format!("{}/repos/{}/{}", self.api_base_url, self.owner, self.repo)
+    }
+
+    pub fn article_path(&self, file_name: &str) -> String {
+        format!("{}/{}", self.articles_dir, file_name)
+    }
+
+    pub fn asset_path(&self, file_name: &str) -> String {
+        format!("{}/{}", self.assets_dir, file_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_toml_keeps_defaults_for_the_rest() {
+        let config =
+            AppConfig::from_toml_str("repo = \"StagingWebsite\"\nforge = \"gitea\"").unwrap();
+        assert_eq!(
+            config,
+            AppConfig {
+                repo: "StagingWebsite".to_string(),
+                forge: ForgeKind::Gitea,
+                ..AppConfig::default()
+            }
+        );
+        assert_eq!(AppConfig::from_toml_str("").unwrap(), AppConfig::default());
+        assert!(AppConfig::from_toml_str("forge = \"gitlab\"").is_err());
+        assert!(AppConfig::from_toml_str("repo = 3").is_err());
+    }
+
+    #[test]
+    fn slashes_are_trimmed_from_paths_and_urls() {
+        let config = AppConfig::from_toml_str(
+            r#"
+articles_dir = "/src/articles/"
+assets_dir = " static/images// "
+api_base_url = "https://git.example.com/api/v1/"
+web_base_url = "https://git.example.com/"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.articles_dir, "src/articles");
+        assert_eq!(config.assets_dir, "static/images");
+        assert_eq!(config.api_base_url, "https://git.example.com/api/v1");
+        assert_eq!(config.web_base_url, "https://git.example.com");
+        assert_eq!(config.article_path("a.toml"), "src/articles/a.toml");
+        assert_eq!(
+            config.repo_api_url(),
+            "https://git.example.com/api/v1/repos/thebeakers/TheBeakersWebsite"
+        );
+    }
+
+    /// The only test touching `BEAKERS_*`, tests run in parallel and the
+    /// environment is shared.
+    #[test]
+    fn env_overrides_apply_per_field() {
+        let overrides = [
+            ("BEAKERS_FORGE", "Forgejo"),
+            ("BEAKERS_OWNER", "someone"),
+            ("BEAKERS_REPO", "Fork"),
+            ("BEAKERS_BRANCH", "staging"),
+            ("BEAKERS_ARTICLES_DIR", "content/articles/"),
+            ("BEAKERS_ASSETS_DIR", "/static/img"),
+            ("BEAKERS_CLIENT_ID", "abc123"),
+            ("BEAKERS_API_BASE_URL", "https://git.example.com/api/v1/"),
+            ("BEAKERS_WEB_BASE_URL", "https://git.example.com"),
+        ];
+        for (var, value) in overrides {
+            env::set_var(var, value);
+        }
+        let mut config = AppConfig::default();
+        config.apply_env_overrides();
+        assert_eq!(
+            config,
+            AppConfig {
+                forge: ForgeKind::Gitea,
+                owner: "someone".to_string(),
+                repo: "Fork".to_string(),
+                branch: "staging".to_string(),
+                articles_dir: "content/articles".to_string(),
+                assets_dir: "static/img".to_string(),
+                client_id: "abc123".to_string(),
+                api_base_url: "https://git.example.com/api/v1".to_string(),
+                web_base_url: "https://git.example.com".to_string(),
+            }
+        );
+
+        // A bad forge and blank values leave the config as it was
+        env::set_var("BEAKERS_FORGE", "gitlab");
+        env::set_var("BEAKERS_REPO", "  ");
+        let mut config = AppConfig::default();
+        config.apply_env_overrides();
+        assert_eq!(config.forge, ForgeKind::GitHub);
+        assert_eq!(config.repo, "TheBeakersWebsite");
+        assert_eq!(config.owner, "someone");
+
+        for (var, _) in overrides {
+            env::remove_var(var);
+        }
+        let mut config = AppConfig::default();
+        config.apply_env_overrides();
+        assert_eq!(config, AppConfig::default());
+    }
+}
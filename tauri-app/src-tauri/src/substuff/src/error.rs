@@ -0,0 +1,395 @@
+use crate::github_http::header_u64;
+use crate::validation::{Diagnostic, Severity};
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+
+/// Error from any of substuff's GitHub REST calls.
+///
+/// Serialized with a `kind` tag so the frontend can react to the cause (send
+/// the user back through login, wait out a rate limit, show a merge view...)
+/// instead of pattern matching on message strings. `path` is the request,
+/// e.g. `PUT /repos/thebeakers/TheBeakersWebsite/contents/src/articles/a.toml`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GitHubError {
+    /// 401, the token is missing, expired or was revoked.
+    Unauthorized {
+        path: String,
+        message: String,
+        documentation_url: Option<String>,
+    },
+    /// 403 that isn't a rate limit, the token lacks access to the resource.
+    Forbidden {
+        path: String,
+        message: String,
+        documentation_url: Option<String>,
+    },
+    /// Primary or secondary rate limit. `reset_at` is a unix timestamp from
+    /// `X-RateLimit-Reset`, `retry_after` is seconds from `Retry-After`.
+    RateLimited {
+        status: u16,
+        path: String,
+        message: String,
+        documentation_url: Option<String>,
+        reset_at: Option<u64>,
+        retry_after: Option<u64>,
+    },
+    NotFound {
+        path: String,
+        message: String,
+        documentation_url: Option<String>,
+    },
+    /// The file on GitHub is not the version the caller loaded, someone else
+    /// changed (or created) it in the meantime. Straight from a 409 or 422
+    /// response `remote_sha` and `remote_content` are empty, GitHub doesn't
+    /// send them.
+    Conflict {
+        path: String,
+        expected_sha: Option<String>,
+        remote_sha: String,
+        remote_content: String,
+    },
+    /// Other articles link to the article's slug, deleting or renaming it
+    /// would break those links. Refused before anything was changed.
+    StillReferenced {
+        path: String,
+        slug: String,
+        referenced_by: Vec<String>,
+    },
+    /// `validate` found errors in the article, nothing was uploaded.
+    InvalidArticle { diagnostics: Vec<Diagnostic> },
+    /// Any other non-success status.
+    Api {
+        status: u16,
+        path: String,
+        message: String,
+        documentation_url: Option<String>,
+    },
+    /// The request never got a response.
+    Network { path: String, message: String },
+    /// GitHub answered with something we couldn't make sense of.
+    InvalidResponse { path: String, message: String },
+    /// Bad input caught before talking to GitHub.
+    InvalidRequest { message: String },
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubErrorResponse {
+    message: String,
+    documentation_url: Option<String>,
+}
+
+impl GitHubError {
+    /// Classifies a non-success response from its status, headers and body.
+    pub fn from_response_parts(
+        path: &str,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Self {
+        let (message, documentation_url) = match serde_json::from_str::<GitHubErrorResponse>(body) {
+            Ok(gh_error) => (gh_error.message, gh_error.documentation_url),
+            Err(_) if body.trim().is_empty() => (status.to_string(), None),
+            Err(_) => (body.to_string(), None),
+        };
+        let path = path.to_string();
+
+        let remaining = header_u64(headers, "x-ratelimit-remaining");
+        let retry_after = header_u64(headers, "retry-after");
+        let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::FORBIDDEN
+                && (remaining == Some(0)
+                    || retry_after.is_some()
+                    || message.to_lowercase().contains("rate limit")));
+
+        match status {
+            _ if rate_limited => GitHubError::RateLimited {
+                status: status.as_u16(),
+                path,
+                message,
+                documentation_url,
+                reset_at: header_u64(headers, "x-ratelimit-reset"),
+                retry_after,
+            },
+            StatusCode::UNAUTHORIZED => GitHubError::Unauthorized {
+                path,
+                message,
+                documentation_url,
+            },
+            StatusCode::FORBIDDEN => GitHubError::Forbidden {
+                path,
+                message,
+                documentation_url,
+            },
+            StatusCode::NOT_FOUND => GitHubError::NotFound {
+                path,
+                message,
+                documentation_url,
+            },
+            StatusCode::CONFLICT | StatusCode::UNPROCESSABLE_ENTITY => GitHubError::Conflict {
+                path,
+                expected_sha: None,
+                remote_sha: String::new(),
+                remote_content: String::new(),
+            },
+            _ => GitHubError::Api {
+                status: status.as_u16(),
+                path,
+                message,
+                documentation_url,
+            },
+        }
+    }
+
+    pub fn network(path: &str, error: impl std::fmt::Display) -> Self {
+        GitHubError::Network {
+            path: path.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn invalid_response(path: &str, error: impl std::fmt::Display) -> Self {
+        GitHubError::InvalidResponse {
+            path: path.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    /// HTTP status GitHub answered with, if it answered at all.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            GitHubError::Unauthorized { .. } => Some(401),
+            GitHubError::Forbidden { .. } => Some(403),
+            GitHubError::RateLimited { status, .. } | GitHubError::Api { status, .. } => {
+                Some(*status)
+            }
+            GitHubError::NotFound { .. } => Some(404),
+            GitHubError::Conflict { .. } => Some(409),
+            GitHubError::StillReferenced { .. }
+            | GitHubError::InvalidArticle { .. }
+            | GitHubError::Network { .. }
+            | GitHubError::InvalidResponse { .. }
+            | GitHubError::InvalidRequest { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for GitHubError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitHubError::Unauthorized { path, message, .. } => write!(
+                f,
+                "GitHub rejected the token ({}): {}. Please log in again.",
+                path, message
+            ),
+            GitHubError::Forbidden { path, message, .. } => {
+                write!(
+                    f,
+                    "GitHub API error ({}): 403 Forbidden - {}",
+                    path, message
+                )
+            }
+            GitHubError::RateLimited { path, message, .. } => {
+                write!(f, "GitHub rate limit hit ({}): {}", path, message)
+            }
+            GitHubError::NotFound { path, message, .. } => {
+                write!(
+                    f,
+                    "GitHub API error ({}): 404 Not Found - {}",
+                    path, message
+                )
+            }
+            GitHubError::Conflict {
+                path, remote_sha, ..
+            } if remote_sha.is_empty() => {
+                write!(f, "{} was changed on GitHub since it was loaded", path)
+            }
+            GitHubError::Conflict {
+                path, remote_sha, ..
+            } => write!(
+                f,
+                "{} was changed on GitHub since it was loaded (remote SHA: {})",
+                path, remote_sha
+            ),
+            GitHubError::StillReferenced {
+                path,
+                slug,
+                referenced_by,
+            } => write!(
+                f,
+                "{} is still linked as {} from {}",
+                path,
+                slug,
+                referenced_by.join(", ")
+            ),
+            GitHubError::InvalidArticle { diagnostics } => {
+                let errors: Vec<String> = diagnostics
+                    .iter()
+                    .filter(|d| d.severity == Severity::Error)
+                    .map(|d| format!("{}: {}", d.field, d.message))
+                    .collect();
+                write!(f, "The article has errors: {}", errors.join("; "))
+            }
+            GitHubError::Api {
+                status,
+                path,
+                message,
+                ..
+            } => write!(f, "GitHub API error ({}): {} - {}", path, status, message),
+            GitHubError::Network { path, message } => {
+                write!(f, "Network error ({}): {}", path, message)
+            }
+            GitHubError::InvalidResponse { path, message } => {
+                write!(
+                    f,
+                    "Failed to parse GitHub API response ({}): {}",
+                    path, message
+                )
+            }
+            GitHubError::InvalidRequest { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for GitHubError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    const PATH: &str = "PUT /repos/o/r/contents/a.toml";
+
+    fn classify(status: u16, headers: &[(&'static str, &'static str)], body: &str) -> GitHubError {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            header_map.insert(*name, HeaderValue::from_static(value));
+        }
+        GitHubError::from_response_parts(
+            PATH,
+            StatusCode::from_u16(status).unwrap(),
+            &header_map,
+            body,
+        )
+    }
+
+    fn json(message: &str) -> String {
+        serde_json::json!({
+            "message": message,
+            "documentation_url": "https://docs.github.com/rest"
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn exhausted_403_is_rate_limited() {
+        let error = classify(
+            403,
+            &[
+                ("x-ratelimit-remaining", "0"),
+                ("x-ratelimit-reset", "1700000000"),
+            ],
+            &json("API rate limit exceeded for user ID 1."),
+        );
+        assert_eq!(
+            error,
+            GitHubError::RateLimited {
+                status: 403,
+                path: PATH.to_string(),
+                message: "API rate limit exceeded for user ID 1.".to_string(),
+                documentation_url: Some("https://docs.github.com/rest".to_string()),
+                reset_at: Some(1_700_000_000),
+                retry_after: None,
+            }
+        );
+    }
+
+    #[test]
+    fn secondary_rate_limit_is_recognized_by_message_or_retry_after() {
+        let by_message = classify(403, &[], &json("You have exceeded a secondary rate limit."));
+        assert!(matches!(by_message, GitHubError::RateLimited { .. }));
+        let by_header = classify(403, &[("retry-after", "30")], &json("Slow down"));
+        assert!(matches!(
+            by_header,
+            GitHubError::RateLimited {
+                retry_after: Some(30),
+                ..
+            }
+        ));
+        let too_many = classify(429, &[], "");
+        assert!(matches!(
+            too_many,
+            GitHubError::RateLimited { status: 429, .. }
+        ));
+    }
+
+    #[test]
+    fn plain_403_is_forbidden() {
+        let error = classify(
+            403,
+            &[("x-ratelimit-remaining", "4999")],
+            &json("Resource not accessible by integration"),
+        );
+        assert_eq!(
+            error,
+            GitHubError::Forbidden {
+                path: PATH.to_string(),
+                message: "Resource not accessible by integration".to_string(),
+                documentation_url: Some("https://docs.github.com/rest".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn auth_and_missing_resources() {
+        assert!(matches!(
+            classify(401, &[], &json("Bad credentials")),
+            GitHubError::Unauthorized { message, .. } if message == "Bad credentials"
+        ));
+        assert!(matches!(
+            classify(404, &[], &json("Not Found")),
+            GitHubError::NotFound { message, .. } if message == "Not Found"
+        ));
+    }
+
+    #[test]
+    fn sha_mismatches_are_conflicts() {
+        for status in [409, 422] {
+            let error = classify(status, &[], &json("a.toml does not match abc"));
+            assert_eq!(
+                error,
+                GitHubError::Conflict {
+                    path: PATH.to_string(),
+                    expected_sha: None,
+                    remote_sha: String::new(),
+                    remote_content: String::new(),
+                }
+            );
+            assert_eq!(error.status(), Some(409));
+        }
+    }
+
+    #[test]
+    fn body_without_json_falls_back_to_status_or_text() {
+        assert!(matches!(
+            classify(502, &[], ""),
+            GitHubError::Api { status: 502, message, documentation_url: None, .. }
+                if message == "502 Bad Gateway"
+        ));
+        assert!(matches!(
+            classify(500, &[], "  \n"),
+            GitHubError::Api { message, .. } if message == "500 Internal Server Error"
+        ));
+        assert!(matches!(
+            classify(500, &[], "<html>oops</html>"),
+            GitHubError::Api { message, .. } if message == "<html>oops</html>"
+        ));
+        assert!(matches!(
+            classify(404, &[], r#"{"message":"Not Found"}"#),
+            GitHubError::NotFound {
+                documentation_url: None,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,429 @@
+use crate::config::{AppConfig, ForgeKind};
+use crate::gitea::GiteaForge;
+use crate::{
+    conflict_if_changed, delete_github_file, ensure_github_branch, get_github_blob,
+    get_github_branch_head, get_github_commit_author, get_github_file, get_github_file_at,
+    github_whoami, list_files_in_github_path, list_github_tree, move_github_file,
+    open_or_update_pull_request, revoke_github_token, start_git_auth, upload_file_to_github,
+    upload_files_to_github, upload_files_to_github_on, wait_for_github, AuthPollHandler,
+    CommitAuthor, GitHubConfig, GitHubError, GitHubFile, GitHubFileUpload,
+    GithubDeviceCodeResponse, OAuthError, PullRequestInfo, RemoteFile,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// What the repo endpoint says the token's user may do with the repo.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct RepoPermissions {
+    pub admin: bool,
+    pub push: bool,
+    pub pull: bool,
+}
+
+/// Who a token belongs to and whether it can publish to the configured repo.
+#[derive(Serialize, Debug, Clone)]
+pub struct WhoAmI {
+    pub login: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    /// OAuth scopes from `X-OAuth-Scopes`. `None` when the server doesn't
+    /// report them (fine-grained tokens, Gitea).
+    pub scopes: Option<Vec<String>>,
+    /// Scopes publishing needs that the token wasn't granted.
+    pub missing_scopes: Vec<String>,
+    /// `owner/repo` the permissions are for.
+    pub repo: String,
+    /// False when the repo is private and the token can't see it (or it
+    /// doesn't exist), the forge answers 404 for both.
+    pub repo_visible: bool,
+    pub permissions: RepoPermissions,
+}
+
+impl WhoAmI {
+    /// Whether uploads to the configured repo can work with this token.
+    pub fn can_publish(&self) -> bool {
+        self.repo_visible && self.permissions.push && self.missing_scopes.is_empty()
+    }
+}
+
+/// Everything the app needs from the server hosting the website repo: login,
+/// reading and writing files, branches and pull requests.
+///
+/// Paths are relative to the repo root and every write lands on a branch of
+/// `config().owner/config().repo`. REST failures are reported as
+/// `GitHubError` whichever forge produced them.
+#[async_trait]
+pub trait Forge: Send + Sync + std::fmt::Debug {
+    fn config(&self) -> &AppConfig;
+
+    /// Starts the OAuth device flow, the user then enters `user_code` at
+    /// `verification_uri`.
+    async fn start_device_flow(&self) -> Result<GithubDeviceCodeResponse>;
+
+    /// Polls until the device code from `start_device_flow` is approved and
+    /// returns the access token. Stops with `OAuthError::Cancelled` once
+    /// `handler` is cancelled.
+    async fn wait_for_device_token(
+        &self,
+        device_code: GithubDeviceCodeResponse,
+        handler: &AuthPollHandler,
+    ) -> Result<String, OAuthError>;
+
+    /// Reads a file from the configured branch, `None` if it does not exist.
+    async fn get_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Option<RemoteFile>, GitHubError>;
+
+    /// Lists one directory on the configured branch.
+    async fn list_dir(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Vec<GitHubFile>, GitHubError>;
+
+    /// Lists every file and directory below `path_in_repo` on the
+    /// configured branch.
+    async fn list_tree(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Vec<GitHubFile>, GitHubError>;
+
+    /// Creates or updates one file on the configured branch. `expected_sha`
+    /// is the blob SHA it was loaded from, `None` for a new file, and a
+    /// mismatch comes back as `GitHubError::Conflict`.
+    async fn put_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_message: &str,
+        file_content: &str,
+        expected_sha: Option<&str>,
+    ) -> Result<String, GitHubError>;
+
+    /// Deletes one file from the configured branch and returns the commit
+    /// SHA. `expected_sha` must be its current blob SHA, a mismatch comes
+    /// back as `GitHubError::Conflict`.
+    async fn delete_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_message: &str,
+        expected_sha: &str,
+    ) -> Result<String, GitHubError>;
+
+    /// Moves one file on the configured branch in a single commit and returns
+    /// the commit SHA. Conflicts if the file isn't at `expected_sha` or
+    /// `to_path` already exists.
+    async fn move_file(
+        &self,
+        token: &str,
+        from_path: &str,
+        to_path: &str,
+        commit_message: &str,
+        expected_sha: &str,
+    ) -> Result<String, GitHubError>;
+
+    /// Content of a blob by SHA, works for files deleted since.
+    async fn get_blob(&self, token: &str, blob_sha: &str) -> Result<String, GitHubError>;
+
+    /// Writes all `files` to `branch` as a single commit.
+    async fn commit_files(
+        &self,
+        token: &str,
+        branch: &str,
+        commit_message: &str,
+        files: &[GitHubFileUpload],
+    ) -> Result<String, GitHubError>;
+
+    /// SHA of the commit `branch` points at.
+    async fn branch_head(&self, token: &str, branch: &str) -> Result<String, GitHubError>;
+
+    /// A file as it is in commit `commit_sha`, `None` if it isn't there.
+    async fn get_file_at(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_sha: &str,
+    ) -> Result<Option<RemoteFile>, GitHubError>;
+
+    /// `commit_files` for files read at `parent_sha`. Anything changed on
+    /// `branch` since makes it fail with `GitHubError::Conflict` rather than
+    /// be overwritten.
+    async fn commit_files_on(
+        &self,
+        token: &str,
+        branch: &str,
+        parent_sha: &str,
+        commit_message: &str,
+        files: &[GitHubFileUpload],
+    ) -> Result<String, GitHubError>;
+
+    /// Creates `branch` from the tip of `base` unless it already exists.
+    async fn ensure_branch(&self, token: &str, branch: &str, base: &str)
+        -> Result<(), GitHubError>;
+
+    /// Opens a PR from `head` into `base`, or updates the one already open.
+    async fn open_or_update_pull_request(
+        &self,
+        token: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequestInfo, GitHubError>;
+
+    /// The user the token belongs to, as a commit author.
+    async fn commit_author(&self, token: &str) -> Result<CommitAuthor, GitHubError>;
+
+    /// The token's user, scopes and permissions on the configured repo.
+    async fn whoami(&self, token: &str) -> Result<WhoAmI, GitHubError>;
+
+    /// Revokes `token` on the server. `Ok(false)` means the forge offers no
+    /// way to do that with the token alone, the user has to revoke it at
+    /// `token_settings_url`.
+    async fn revoke_token(&self, token: &str) -> Result<bool, GitHubError>;
+
+    /// Page where the user can see and revoke what the app has access to.
+    fn token_settings_url(&self) -> String;
+
+    /// Returns `GitHubError::Conflict` if the file is no longer the version
+    /// with `expected_sha` (`None` meaning it should not exist yet).
+    async fn ensure_file_unchanged(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        expected_sha: Option<&str>,
+    ) -> Result<(), GitHubError> {
+        let remote = self.get_file(token, path_in_repo).await?;
+        conflict_if_changed(remote, path_in_repo, expected_sha)
+    }
+}
+
+/// The forge selected by `config.forge`. `client` is cloned around freely,
+/// every clone shares one connection pool.
+pub fn forge_for_config(config: AppConfig, client: Client) -> Box<dyn Forge> {
+    match config.forge {
+        ForgeKind::GitHub => Box::new(GitHubForge::new(config, client)),
+        ForgeKind::Gitea => Box::new(GiteaForge::new(config, client)),
+    }
+}
+
+/// github.com or GitHub Enterprise Server.
+#[derive(Debug, Clone)]
+pub struct GitHubForge {
+    config: AppConfig,
+    client: Client,
+}
+
+impl GitHubForge {
+    pub fn new(config: AppConfig, client: Client) -> Self {
+        GitHubForge { config, client }
+    }
+}
+
+#[async_trait]
+impl Forge for GitHubForge {
+    fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    async fn start_device_flow(&self) -> Result<GithubDeviceCodeResponse> {
+        start_git_auth(&self.client, &self.config).await
+    }
+
+    async fn wait_for_device_token(
+        &self,
+        device_code: GithubDeviceCodeResponse,
+        handler: &AuthPollHandler,
+    ) -> Result<String, OAuthError> {
+        wait_for_github(
+            &self.client,
+            GitHubConfig::new(&self.config, device_code),
+            handler,
+        )
+        .await
+    }
+
+    async fn get_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Option<RemoteFile>, GitHubError> {
+        get_github_file(&self.client, &self.config, token, path_in_repo).await
+    }
+
+    async fn list_dir(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Vec<GitHubFile>, GitHubError> {
+        list_files_in_github_path(&self.client, &self.config, token, path_in_repo).await
+    }
+
+    async fn list_tree(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Vec<GitHubFile>, GitHubError> {
+        list_github_tree(&self.client, &self.config, token, path_in_repo).await
+    }
+
+    async fn put_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_message: &str,
+        file_content: &str,
+        expected_sha: Option<&str>,
+    ) -> Result<String, GitHubError> {
+        upload_file_to_github(
+            &self.client,
+            &self.config,
+            token,
+            path_in_repo,
+            commit_message,
+            file_content,
+            expected_sha,
+        )
+        .await
+    }
+
+    async fn commit_files(
+        &self,
+        token: &str,
+        branch: &str,
+        commit_message: &str,
+        files: &[GitHubFileUpload],
+    ) -> Result<String, GitHubError> {
+        upload_files_to_github(
+            &self.client,
+            &self.config,
+            token,
+            branch,
+            commit_message,
+            files,
+        )
+        .await
+    }
+
+    async fn branch_head(&self, token: &str, branch: &str) -> Result<String, GitHubError> {
+        get_github_branch_head(&self.client, &self.config, token, branch).await
+    }
+
+    async fn get_file_at(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_sha: &str,
+    ) -> Result<Option<RemoteFile>, GitHubError> {
+        get_github_file_at(&self.client, &self.config, token, path_in_repo, commit_sha).await
+    }
+
+    async fn commit_files_on(
+        &self,
+        token: &str,
+        branch: &str,
+        parent_sha: &str,
+        commit_message: &str,
+        files: &[GitHubFileUpload],
+    ) -> Result<String, GitHubError> {
+        upload_files_to_github_on(
+            &self.client,
+            &self.config,
+            token,
+            branch,
+            parent_sha,
+            commit_message,
+            files,
+        )
+        .await
+    }
+
+    async fn delete_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_message: &str,
+        expected_sha: &str,
+    ) -> Result<String, GitHubError> {
+        delete_github_file(
+            &self.client,
+            &self.config,
+            token,
+            path_in_repo,
+            commit_message,
+            expected_sha,
+        )
+        .await
+    }
+
+    async fn move_file(
+        &self,
+        token: &str,
+        from_path: &str,
+        to_path: &str,
+        commit_message: &str,
+        expected_sha: &str,
+    ) -> Result<String, GitHubError> {
+        move_github_file(
+            &self.client,
+            &self.config,
+            token,
+            from_path,
+            to_path,
+            commit_message,
+            expected_sha,
+        )
+        .await
+    }
+
+    async fn get_blob(&self, token: &str, blob_sha: &str) -> Result<String, GitHubError> {
+        get_github_blob(&self.client, &self.config, token, blob_sha).await
+    }
+
+    async fn ensure_branch(
+        &self,
+        token: &str,
+        branch: &str,
+        base: &str,
+    ) -> Result<(), GitHubError> {
+        ensure_github_branch(&self.client, &self.config, token, branch, base).await
+    }
+
+    async fn open_or_update_pull_request(
+        &self,
+        token: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequestInfo, GitHubError> {
+        open_or_update_pull_request(&self.client, &self.config, token, head, base, title, body)
+            .await
+    }
+
+    async fn commit_author(&self, token: &str) -> Result<CommitAuthor, GitHubError> {
+        get_github_commit_author(&self.client, &self.config, token).await
+    }
+
+    async fn whoami(&self, token: &str) -> Result<WhoAmI, GitHubError> {
+        github_whoami(&self.client, &self.config, token).await
+    }
+
+    async fn revoke_token(&self, token: &str) -> Result<bool, GitHubError> {
+        revoke_github_token(&self.client, &self.config, token).await
+    }
+
+    fn token_settings_url(&self) -> String {
+        format!(
+            "{}/settings/connections/applications/{}",
+            self.config.web_base_url, self.config.client_id
+        )
+    }
+}
@@ -0,0 +1,13 @@
+use std::fs;
+use std::path::Path;
+
+/// Writes the JSON Schema and TypeScript definitions generated from the
+/// article types into `Types/` at the repo root.
+fn main() -> anyhow::Result<()> {
+    let repo_root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../..");
+    for (path, content) in substuff::generated_files() {
+        fs::write(repo_root.join(path), content)?;
+        println!("Wrote {}", path);
+    }
+    Ok(())
+}
@@ -13,6 +13,7 @@
 // }
 // fn update_file_via_api(pac_token: String, file_path: &Path) {}
 
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
 use reqwest::{Client, Error};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -27,7 +28,7 @@ struct FileContent {
 async fn update_file_via_api(
     pac_token: String,
     file_path: &Path,
-    repo_link: String,
+    _repo_link: String,
 ) -> Result<(), Error> {
     let repo_owner = "thebeakers"; // Replace with the actual owner of the repo
     let repo_name = "TheBeakersWebsite"; // Replace with the actual repository name
@@ -54,7 +55,7 @@ async fn update_file_via_api(
     let current_sha = &current_file.sha.unwrap_or_default();
 
     // Prepare the new content to update
-    let new_content = base64::encode("New file content goes here"); // Update the content here
+    let new_content = BASE64_STANDARD.encode("New file content goes here"); // Update the content here
     let message = "Updating file content via API";
 
     let update_content = FileContent {
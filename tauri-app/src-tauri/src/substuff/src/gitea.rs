@@ -0,0 +1,725 @@
+use crate::config::AppConfig;
+use crate::forge::{Forge, RepoPermissions, WhoAmI};
+use crate::github_http::{parse_github_json, send_github, send_github_json};
+use crate::{
+    conflict_if_changed, decode_blob_content, AuthPollHandler, CommitAuthor, GitHubError,
+    GitHubFile, GitHubFileUpload, GitTreeResponse, GithubDeviceCodeResponse, OAuthError,
+    PullRequestInfo, RemoteFile,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+use reqwest::{header::AUTHORIZATION, Client, Method};
+use serde::{Deserialize, Serialize};
+
+/// Most tree pages Gitea hands out per request.
+const TREE_PAGE_SIZE: &str = "1000";
+
+/// Gitea or Forgejo through its `/api/v1` REST API.
+///
+/// Gitea has no OAuth device flow, so users sign in with an access token
+/// (created under Settings > Applications, with repository write access).
+#[derive(Debug, Clone)]
+pub struct GiteaForge {
+    config: AppConfig,
+    client: Client,
+}
+
+impl GiteaForge {
+    pub fn new(config: AppConfig, client: Client) -> Self {
+        GiteaForge { config, client }
+    }
+
+    fn request(&self, method: Method, url: &str, token: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, url)
+            .header(AUTHORIZATION, format!("token {}", token))
+            .header("Accept", "application/json")
+            .header("User-Agent", "TauriProfessorApp/0.1.0")
+    }
+
+    fn contents_url(&self, path_in_repo: &str) -> String {
+        format!("{}/contents/{}", self.config.repo_api_url(), path_in_repo)
+    }
+
+    async fn file_on_branch(
+        &self,
+        token: &str,
+        branch: &str,
+        path_in_repo: &str,
+    ) -> Result<Option<RemoteFile>, GitHubError> {
+        Ok(self
+            .file_bytes_on_branch(token, branch, path_in_repo)
+            .await?
+            .map(|(sha, bytes)| RemoteFile {
+                sha,
+                content: String::from_utf8_lossy(&bytes).into_owned(),
+            }))
+    }
+
+    /// Blob SHA and raw content of a file, for files that may not be text.
+    async fn file_bytes_on_branch(
+        &self,
+        token: &str,
+        branch: &str,
+        path_in_repo: &str,
+    ) -> Result<Option<(String, Vec<u8>)>, GitHubError> {
+        let resp = match send_github(
+            self.request(Method::GET, &self.contents_url(path_in_repo), token)
+                .query(&[("ref", branch)]),
+        )
+        .await
+        {
+            Ok(resp) => resp,
+            Err(GitHubError::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let request_path = resp.url().path().to_string();
+        let file_data: GiteaContentsResponse = parse_github_json(resp).await?;
+        let encoded: String = file_data
+            .content
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let bytes = BASE64_STANDARD
+            .decode(encoded)
+            .map_err(|e| GitHubError::invalid_response(&request_path, e))?;
+        Ok(Some((file_data.sha, bytes)))
+    }
+
+    /// Writes `files` to `branch` in one commit through the ChangeFiles API.
+    /// Updates have to name the blob they replace, those SHAs are taken
+    /// from `base_ref`. Gitea can't pin a commit to a parent, but it checks
+    /// each SHA against the branch, so with `base_ref` a commit any file
+    /// changed since is a conflict.
+    async fn change_files(
+        &self,
+        token: &str,
+        branch: &str,
+        base_ref: &str,
+        commit_message: &str,
+        files: &[GitHubFileUpload],
+    ) -> Result<String, GitHubError> {
+        if files.is_empty() {
+            return Err(GitHubError::InvalidRequest {
+                message: "No files given to upload".to_string(),
+            });
+        }
+        let mut operations = Vec::with_capacity(files.len());
+        for file in files {
+            let existing = self.file_on_branch(token, base_ref, &file.path).await?;
+            operations.push(GiteaChangeFileOperation {
+                operation: if existing.is_some() {
+                    "update"
+                } else {
+                    "create"
+                },
+                path: &file.path,
+                content: BASE64_STANDARD.encode(&file.content),
+                sha: existing.map(|remote| remote.sha),
+                from_path: None,
+            });
+        }
+
+        let file_response: GiteaFileResponse = send_github_json(
+            self.request(
+                Method::POST,
+                &format!("{}/contents", self.config.repo_api_url()),
+                token,
+            )
+            .json(&GiteaChangeFilesRequest {
+                files: operations,
+                message: commit_message,
+                branch,
+                committer: COMMITTER,
+            }),
+        )
+        .await?;
+
+        let success_msg = format!(
+            "{} file(s) committed to {}/{} ({}). Commit SHA: {}",
+            files.len(),
+            self.config.owner,
+            self.config.repo,
+            branch,
+            file_response.commit.sha
+        );
+        log::info!("{}", success_msg);
+        Ok(success_msg)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaContentsResponse {
+    sha: String,
+    content: Option<String>, // Base64 encoded
+}
+
+#[derive(Serialize, Debug)]
+struct GiteaIdentity<'a> {
+    name: &'a str,
+    email: &'a str,
+}
+
+const COMMITTER: GiteaIdentity<'static> = GiteaIdentity {
+    name: "Professor App",
+    email: "app@thebeakers.com",
+};
+
+#[derive(Serialize, Debug)]
+struct GiteaFileRequest<'a> {
+    message: &'a str,
+    content: String, // Base64 encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha: Option<&'a str>, // Required for updates, absent for creates
+    branch: &'a str,
+    committer: GiteaIdentity<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct GiteaChangeFileOperation<'a> {
+    operation: &'static str, // "create" or "update"
+    path: &'a str,
+    content: String, // Base64 encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_path: Option<&'a str>, // Set on an update that also moves the file
+}
+
+#[derive(Serialize, Debug)]
+struct GiteaDeleteFileRequest<'a> {
+    message: &'a str,
+    sha: &'a str,
+    branch: &'a str,
+    committer: GiteaIdentity<'a>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaBlobResponse {
+    content: String,
+    encoding: String,
+}
+
+#[derive(Serialize, Debug)]
+struct GiteaChangeFilesRequest<'a> {
+    files: Vec<GiteaChangeFileOperation<'a>>,
+    message: &'a str,
+    branch: &'a str,
+    committer: GiteaIdentity<'a>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaFileResponse {
+    commit: GiteaCommitInfo,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaCommitInfo {
+    sha: String,
+}
+
+#[derive(Serialize, Debug)]
+struct GiteaCreateBranchRequest<'a> {
+    new_branch_name: &'a str,
+    old_branch_name: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaBranch {
+    commit: GiteaBranchCommit,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaBranchCommit {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaBranchRef {
+    r#ref: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaPullRequest {
+    number: u64,
+    html_url: String,
+    head: GiteaBranchRef,
+    base: GiteaBranchRef,
+}
+
+#[derive(Serialize, Debug)]
+struct GiteaPullRequestRequest<'a> {
+    title: &'a str,
+    body: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaUser {
+    login: String,
+    full_name: Option<String>,
+    email: Option<String>,
+    avatar_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaRepoResponse {
+    full_name: String,
+    #[serde(default)]
+    permissions: RepoPermissions,
+}
+
+/// Page size for listing pull requests, Gitea's default maximum.
+const PAGE_LIMIT: usize = 50;
+
+#[async_trait]
+impl Forge for GiteaForge {
+    fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    async fn start_device_flow(&self) -> Result<GithubDeviceCodeResponse> {
+        Err(anyhow::anyhow!(
+            "{} does not support device login, sign in with an access token instead",
+            self.config.web_base_url
+        ))
+    }
+
+    async fn wait_for_device_token(
+        &self,
+        _device_code: GithubDeviceCodeResponse,
+        _handler: &AuthPollHandler,
+    ) -> Result<String, OAuthError> {
+        Err(OAuthError::DeviceFlowDisabled)
+    }
+
+    async fn get_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Option<RemoteFile>, GitHubError> {
+        self.file_on_branch(token, &self.config.branch, path_in_repo)
+            .await
+    }
+
+    async fn list_dir(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Vec<GitHubFile>, GitHubError> {
+        send_github_json(
+            self.request(Method::GET, &self.contents_url(path_in_repo), token)
+                .query(&[("ref", &self.config.branch)]),
+        )
+        .await
+    }
+
+    async fn list_tree(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+    ) -> Result<Vec<GitHubFile>, GitHubError> {
+        // Gitea can't list a subtree by path, so page through the whole branch
+        let base = path_in_repo.trim_matches('/');
+        let prefix = format!("{}/", base);
+        let tree_url = format!(
+            "{}/git/trees/{}",
+            self.config.repo_api_url(),
+            self.config.branch
+        );
+        let mut entries = Vec::new();
+        for page in 1.. {
+            let response: GitTreeResponse =
+                send_github_json(self.request(Method::GET, &tree_url, token).query(&[
+                    ("recursive", "true"),
+                    ("per_page", TREE_PAGE_SIZE),
+                    ("page", &page.to_string()),
+                ]))
+                .await?;
+            let last_page = !response.truncated || response.tree.is_empty();
+            entries.extend(
+                response
+                    .tree
+                    .into_iter()
+                    .map(|entry| entry.into_file(""))
+                    .filter(|file| base.is_empty() || file.path.starts_with(&prefix)),
+            );
+            if last_page {
+                break;
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn put_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_message: &str,
+        file_content: &str,
+        expected_sha: Option<&str>,
+    ) -> Result<String, GitHubError> {
+        // Gitea creates with POST and updates with PUT
+        let method = match expected_sha {
+            Some(_) => Method::PUT,
+            None => Method::POST,
+        };
+        let request_body = GiteaFileRequest {
+            message: commit_message,
+            content: BASE64_STANDARD.encode(file_content.as_bytes()),
+            sha: expected_sha,
+            branch: &self.config.branch,
+            committer: COMMITTER,
+        };
+        let response = send_github(
+            self.request(method, &self.contents_url(path_in_repo), token)
+                .json(&request_body),
+        )
+        .await;
+
+        let file_response: GiteaFileResponse = match response {
+            Ok(resp) => parse_github_json(resp).await?,
+            Err(error) => {
+                // Stale SHAs and creates of existing files are validation errors
+                if matches!(error.status(), Some(409) | Some(422)) {
+                    self.ensure_file_unchanged(token, path_in_repo, expected_sha)
+                        .await?;
+                }
+                log::error!("Failed to upload file {}: {}", path_in_repo, error);
+                return Err(error);
+            }
+        };
+
+        let action = if expected_sha.is_some() {
+            "updated"
+        } else {
+            "created"
+        };
+        let success_msg = format!(
+            "File {} successfully {} in {}/{}. Commit SHA: {}",
+            path_in_repo, action, self.config.owner, self.config.repo, file_response.commit.sha
+        );
+        log::info!("{}", success_msg);
+        Ok(success_msg)
+    }
+
+    async fn commit_files(
+        &self,
+        token: &str,
+        branch: &str,
+        commit_message: &str,
+        files: &[GitHubFileUpload],
+    ) -> Result<String, GitHubError> {
+        self.change_files(token, branch, branch, commit_message, files)
+            .await
+    }
+
+    async fn branch_head(&self, token: &str, branch: &str) -> Result<String, GitHubError> {
+        let branch: GiteaBranch = send_github_json(self.request(
+            Method::GET,
+            &format!("{}/branches/{}", self.config.repo_api_url(), branch),
+            token,
+        ))
+        .await?;
+        Ok(branch.commit.id)
+    }
+
+    async fn get_file_at(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_sha: &str,
+    ) -> Result<Option<RemoteFile>, GitHubError> {
+        self.file_on_branch(token, commit_sha, path_in_repo).await
+    }
+
+    async fn commit_files_on(
+        &self,
+        token: &str,
+        branch: &str,
+        parent_sha: &str,
+        commit_message: &str,
+        files: &[GitHubFileUpload],
+    ) -> Result<String, GitHubError> {
+        self.change_files(token, branch, parent_sha, commit_message, files)
+            .await
+    }
+
+    async fn delete_file(
+        &self,
+        token: &str,
+        path_in_repo: &str,
+        commit_message: &str,
+        expected_sha: &str,
+    ) -> Result<String, GitHubError> {
+        let response = send_github(
+            self.request(Method::DELETE, &self.contents_url(path_in_repo), token)
+                .json(&GiteaDeleteFileRequest {
+                    message: commit_message,
+                    sha: expected_sha,
+                    branch: &self.config.branch,
+                    committer: COMMITTER,
+                }),
+        )
+        .await;
+        let file_response: GiteaFileResponse = match response {
+            Ok(resp) => parse_github_json(resp).await?,
+            Err(error) => {
+                if matches!(error.status(), Some(404) | Some(409) | Some(422)) {
+                    self.ensure_file_unchanged(token, path_in_repo, Some(expected_sha))
+                        .await?;
+                }
+                log::error!("Failed to delete file {}: {}", path_in_repo, error);
+                return Err(error);
+            }
+        };
+        log::info!(
+            "Deleted {} from {}/{}. Commit SHA: {}",
+            path_in_repo,
+            self.config.owner,
+            self.config.repo,
+            file_response.commit.sha
+        );
+        Ok(file_response.commit.sha)
+    }
+
+    async fn move_file(
+        &self,
+        token: &str,
+        from_path: &str,
+        to_path: &str,
+        commit_message: &str,
+        expected_sha: &str,
+    ) -> Result<String, GitHubError> {
+        // Gitea moves by updating with `from_path`, which needs the content.
+        // Kept as bytes, images don't survive a detour through a String.
+        let remote = self
+            .file_bytes_on_branch(token, &self.config.branch, from_path)
+            .await?;
+        let Some((remote_sha, bytes)) = remote else {
+            return Err(GitHubError::NotFound {
+                path: from_path.to_string(),
+                message: format!("{} does not exist on the branch", from_path),
+                documentation_url: None,
+            });
+        };
+        if remote_sha != expected_sha {
+            let remote = RemoteFile {
+                sha: remote_sha.clone(),
+                content: String::from_utf8_lossy(&bytes).into_owned(),
+            };
+            conflict_if_changed(Some(remote), from_path, Some(expected_sha))?;
+        }
+        self.ensure_file_unchanged(token, to_path, None).await?;
+
+        let file_response: GiteaFileResponse = send_github_json(
+            self.request(
+                Method::POST,
+                &format!("{}/contents", self.config.repo_api_url()),
+                token,
+            )
+            .json(&GiteaChangeFilesRequest {
+                files: vec![GiteaChangeFileOperation {
+                    operation: "update",
+                    path: to_path,
+                    content: BASE64_STANDARD.encode(&bytes),
+                    sha: Some(remote_sha),
+                    from_path: Some(from_path),
+                }],
+                message: commit_message,
+                branch: &self.config.branch,
+                committer: COMMITTER,
+            }),
+        )
+        .await?;
+        log::info!(
+            "Moved {} to {} in {}/{}. Commit SHA: {}",
+            from_path,
+            to_path,
+            self.config.owner,
+            self.config.repo,
+            file_response.commit.sha
+        );
+        Ok(file_response.commit.sha)
+    }
+
+    async fn get_blob(&self, token: &str, blob_sha: &str) -> Result<String, GitHubError> {
+        let resp = send_github(self.request(
+            Method::GET,
+            &format!("{}/git/blobs/{}", self.config.repo_api_url(), blob_sha),
+            token,
+        ))
+        .await?;
+        let request_path = resp.url().path().to_string();
+        let blob: GiteaBlobResponse = parse_github_json(resp).await?;
+        decode_blob_content(&request_path, &blob.content, &blob.encoding)
+    }
+
+    async fn ensure_branch(
+        &self,
+        token: &str,
+        branch: &str,
+        base: &str,
+    ) -> Result<(), GitHubError> {
+        let branches_url = format!("{}/branches", self.config.repo_api_url());
+        let existing =
+            send_github(self.request(Method::GET, &format!("{}/{}", branches_url, branch), token))
+                .await;
+        match existing {
+            Ok(_) => {
+                log::info!("Branch {} already exists", branch);
+                return Ok(());
+            }
+            Err(GitHubError::NotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        let _: serde_json::Value = send_github_json(
+            self.request(Method::POST, &branches_url, token)
+                .json(&GiteaCreateBranchRequest {
+                    new_branch_name: branch,
+                    old_branch_name: base,
+                }),
+        )
+        .await?;
+        log::info!("Created branch {} from {}", branch, base);
+        Ok(())
+    }
+
+    async fn open_or_update_pull_request(
+        &self,
+        token: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequestInfo, GitHubError> {
+        let pulls_url = format!("{}/pulls", self.config.repo_api_url());
+
+        // The list endpoint can't filter by head, so walk the open PRs
+        let mut existing = None;
+        let mut page = 1;
+        while existing.is_none() {
+            let open_pulls: Vec<GiteaPullRequest> =
+                send_github_json(self.request(Method::GET, &pulls_url, token).query(&[
+                    ("state", "open".to_string()),
+                    ("page", page.to_string()),
+                    ("limit", PAGE_LIMIT.to_string()),
+                ]))
+                .await?;
+            let last_page = open_pulls.len() < PAGE_LIMIT;
+            existing = open_pulls
+                .into_iter()
+                .find(|pull| pull.head.r#ref == head && pull.base.r#ref == base);
+            if last_page {
+                break;
+            }
+            page += 1;
+        }
+
+        let (method, url, request_body, created) = match &existing {
+            Some(pull) => (
+                Method::PATCH,
+                format!("{}/{}", pulls_url, pull.number),
+                GiteaPullRequestRequest {
+                    title,
+                    body,
+                    head: None,
+                    base: None,
+                },
+                false,
+            ),
+            None => (
+                Method::POST,
+                pulls_url,
+                GiteaPullRequestRequest {
+                    title,
+                    body,
+                    head: Some(head),
+                    base: Some(base),
+                },
+                true,
+            ),
+        };
+        let pull: GiteaPullRequest =
+            send_github_json(self.request(method, &url, token).json(&request_body)).await?;
+        log::info!(
+            "{} pull request {}",
+            if created { "Opened" } else { "Updated" },
+            pull.html_url
+        );
+        Ok(PullRequestInfo {
+            number: pull.number,
+            html_url: pull.html_url,
+            created,
+        })
+    }
+
+    async fn commit_author(&self, token: &str) -> Result<CommitAuthor, GitHubError> {
+        let user: GiteaUser = send_github_json(self.request(
+            Method::GET,
+            &format!("{}/user", self.config.api_base_url),
+            token,
+        ))
+        .await?;
+        let name = user
+            .full_name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| user.login.clone());
+        // Same placeholder Gitea itself uses for users without a visible email
+        let email = user
+            .email
+            .filter(|email| !email.trim().is_empty())
+            .unwrap_or_else(|| format!("{}@noreply.localhost", user.login));
+        Ok(CommitAuthor { name, email })
+    }
+
+    async fn whoami(&self, token: &str) -> Result<WhoAmI, GitHubError> {
+        let user: GiteaUser = send_github_json(self.request(
+            Method::GET,
+            &format!("{}/user", self.config.api_base_url),
+            token,
+        ))
+        .await?;
+        let repo = match send_github_json::<GiteaRepoResponse>(self.request(
+            Method::GET,
+            &self.config.repo_api_url(),
+            token,
+        ))
+        .await
+        {
+            Ok(repo) => Some(repo),
+            Err(GitHubError::NotFound { .. }) => None,
+            Err(e) => return Err(e),
+        };
+        // Gitea token scopes aren't exposed over the API, a token without
+        // write:repository shows up as a 403 on the first upload
+        Ok(WhoAmI {
+            login: user.login,
+            name: user.full_name.filter(|name| !name.trim().is_empty()),
+            avatar_url: user.avatar_url,
+            scopes: None,
+            missing_scopes: Vec::new(),
+            repo: repo
+                .as_ref()
+                .map(|repo| repo.full_name.clone())
+                .unwrap_or_else(|| format!("{}/{}", self.config.owner, self.config.repo)),
+            repo_visible: repo.is_some(),
+            permissions: repo.map(|repo| repo.permissions).unwrap_or_default(),
+        })
+    }
+
+    async fn revoke_token(&self, _token: &str) -> Result<bool, GitHubError> {
+        // Deleting an access token needs the account password
+        Ok(false)
+    }
+
+    fn token_settings_url(&self) -> String {
+        format!("{}/user/settings/applications", self.config.web_base_url)
+    }
+}
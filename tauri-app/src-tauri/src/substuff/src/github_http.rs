@@ -0,0 +1,418 @@
+use crate::GitHubError;
+use reqwest::{header::HeaderMap, header::AUTHORIZATION, Client, Method, StatusCode};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Attempts per request, including the first one.
+const MAX_ATTEMPTS: u32 = 4;
+/// Base delay for retrying network errors and 5xx, doubled every attempt.
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// Longest we are willing to sleep for a rate limit before giving up and
+/// handing the `RateLimited` error to the caller.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+/// GitHub asks for at least a minute between retries of a secondary rate
+/// limit that came without a `Retry-After`.
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Rate limit budget from the `X-RateLimit-*` headers of the latest response.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitBudget {
+    pub limit: u64,
+    pub remaining: u64,
+    pub used: u64,
+    pub reset_at: u64, // Unix timestamp
+    pub resource: Option<String>,
+}
+
+static RATE_LIMIT: Mutex<Option<RateLimitBudget>> = Mutex::new(None);
+
+/// The most recent rate limit budget GitHub reported, `None` before the first
+/// REST call of the session.
+pub fn github_rate_limit() -> Option<RateLimitBudget> {
+    RATE_LIMIT.lock().unwrap().clone()
+}
+
+/// A numeric header, e.g. `x-ratelimit-remaining`.
+pub(crate) fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn record_rate_limit(headers: &HeaderMap) {
+    let (Some(limit), Some(remaining), Some(reset_at)) = (
+        header_u64(headers, "x-ratelimit-limit"),
+        header_u64(headers, "x-ratelimit-remaining"),
+        header_u64(headers, "x-ratelimit-reset"),
+    ) else {
+        return;
+    };
+    let budget = RateLimitBudget {
+        limit,
+        remaining,
+        used: header_u64(headers, "x-ratelimit-used").unwrap_or(limit - remaining.min(limit)),
+        reset_at,
+        resource: headers
+            .get("x-ratelimit-resource")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+    };
+    log::debug!("GitHub rate limit: {}/{} left", remaining, limit);
+    *RATE_LIMIT.lock().unwrap() = Some(budget);
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Up to `max` of jitter. Good enough to spread out retries without pulling
+/// in a rand dependency.
+fn jitter(max: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0) as u64;
+    Duration::from_millis(nanos % (max.as_millis() as u64).max(1))
+}
+
+fn backoff(attempt: u32) -> Duration {
+    let exponential = BASE_BACKOFF * 2u32.pow(attempt.saturating_sub(1));
+    exponential + jitter(exponential)
+}
+
+/// How long to wait before retrying a rate limited request, `None` if the
+/// reset is too far away to be worth blocking on.
+fn rate_limit_wait(error: &GitHubError) -> Option<Duration> {
+    let GitHubError::RateLimited {
+        reset_at,
+        retry_after,
+        ..
+    } = error
+    else {
+        return None;
+    };
+    let wait = match (retry_after, reset_at) {
+        (Some(seconds), _) => Duration::from_secs(*seconds),
+        (None, Some(reset_at)) => Duration::from_secs(reset_at.saturating_sub(unix_now()) + 1),
+        (None, None) => SECONDARY_RATE_LIMIT_WAIT,
+    };
+    (wait <= MAX_RATE_LIMIT_WAIT).then(|| wait + jitter(Duration::from_secs(1)))
+}
+
+/// Contents API PUT/DELETE create commits, so a retry after a lost response
+/// would come back as a bogus conflict. Only reads are safe to repeat.
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+pub(crate) fn github_request(
+    client: &Client,
+    method: Method,
+    url: &str,
+    token: &str,
+) -> reqwest::RequestBuilder {
+    client
+        .request(method, url)
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .header("User-Agent", "TauriProfessorApp/0.1.0")
+}
+
+/// Sends a GitHub REST request, turning any non-success status into a
+/// `GitHubError` that carries the request path and GitHub's message.
+///
+/// Rate limited requests are retried once the limit resets (GitHub did not
+/// act on them, so this is safe for any method) as long as that is within a
+/// minute. Network errors and 502/503/504 are retried with jittered backoff,
+/// but only for idempotent methods.
+pub(crate) async fn send_github(
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, GitHubError> {
+    let (client, request) = request.build_split();
+    let request = request.map_err(|e| GitHubError::InvalidRequest {
+        message: e.to_string(),
+    })?;
+    let request_path = format!("{} {}", request.method(), request.url().path());
+    let idempotent = is_idempotent(request.method());
+
+    let mut attempt = 1;
+    loop {
+        // Bodies from .json() are plain bytes, so this only fails for streams
+        let this_try = request
+            .try_clone()
+            .ok_or_else(|| GitHubError::InvalidRequest {
+                message: format!("{} has a body that can't be retried", request_path),
+            })?;
+
+        let error = match client.execute(this_try).await {
+            Ok(resp) => {
+                record_rate_limit(resp.headers());
+                let status = resp.status();
+                log::debug!("{} response status: {}", request_path, status);
+                if status.is_success() {
+                    return Ok(resp);
+                }
+                let headers = resp.headers().clone();
+                let body = resp.text().await.unwrap_or_default();
+                let error =
+                    GitHubError::from_response_parts(&request_path, status, &headers, &body);
+                let retry_after = match &error {
+                    GitHubError::RateLimited { .. } => rate_limit_wait(&error),
+                    _ if idempotent && is_transient(status) => Some(backoff(attempt)),
+                    _ => None,
+                };
+                match retry_after {
+                    Some(wait) if attempt < MAX_ATTEMPTS => {
+                        log::warn!("{}, retrying in {:?}", error, wait);
+                        tokio::time::sleep(wait).await;
+                        attempt += 1;
+                        continue;
+                    }
+                    _ => error,
+                }
+            }
+            Err(e) => {
+                let error = GitHubError::network(&request_path, &e);
+                if idempotent && attempt < MAX_ATTEMPTS {
+                    let wait = backoff(attempt);
+                    log::warn!("{}, retrying in {:?}", error, wait);
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                    continue;
+                }
+                error
+            }
+        };
+        log::error!("{}", error);
+        return Err(error);
+    }
+}
+
+pub(crate) async fn parse_github_json<T: serde::de::DeserializeOwned>(
+    resp: reqwest::Response,
+) -> Result<T, GitHubError> {
+    let request_path = resp.url().path().to_string();
+    resp.json::<T>()
+        .await
+        .map_err(|e| GitHubError::invalid_response(&request_path, e))
+}
+
+pub(crate) async fn send_github_json<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+) -> Result<T, GitHubError> {
+    parse_github_json(send_github(request).await?).await
+}
+
+/// The `rel="next"` URL of a `Link` header, `None` on the last page.
+fn next_page_url(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(reqwest::header::LINK)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .find(|link| link.contains("rel=\"next\""))
+        .and_then(|link| {
+            let start = link.find('<')? + 1;
+            let end = link.find('>')?;
+            Some(link[start..end].to_string())
+        })
+}
+
+/// GETs every page of a GitHub list endpoint, following the `Link` header.
+pub(crate) async fn get_github_pages<T: serde::de::DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    token: &str,
+    query: &[(&str, &str)],
+) -> Result<Vec<T>, GitHubError> {
+    let mut items = Vec::new();
+    // The next links already carry the query, including per_page
+    let mut request = github_request(client, Method::GET, url, token)
+        .query(query)
+        .query(&[("per_page", "100")]);
+    loop {
+        let resp = send_github(request).await?;
+        let next = next_page_url(resp.headers());
+        items.extend(parse_github_json::<Vec<T>>(resp).await?);
+        match next {
+            Some(next) => request = github_request(client, Method::GET, &next, token),
+            None => return Ok(items),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, LINK};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn rate_limited(reset_at: Option<u64>, retry_after: Option<u64>) -> GitHubError {
+        GitHubError::RateLimited {
+            status: 403,
+            path: "GET /user".to_string(),
+            message: "API rate limit exceeded".to_string(),
+            documentation_url: None,
+            reset_at,
+            retry_after,
+        }
+    }
+
+    fn assert_between(wait: Option<Duration>, low: u64, high: u64) {
+        let wait = wait.expect("should wait");
+        assert!(
+            wait >= Duration::from_secs(low) && wait < Duration::from_secs(high),
+            "{:?} not in {}..{}s",
+            wait,
+            low,
+            high
+        );
+    }
+
+    #[test]
+    fn next_page_url_follows_rel_next() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(next_page_url(&headers), None);
+
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                "<https://api.github.com/repositories/1/contents?page=2>; rel=\"next\", \
+                 <https://api.github.com/repositories/1/contents?page=5>; rel=\"last\"",
+            ),
+        );
+        assert_eq!(
+            next_page_url(&headers).as_deref(),
+            Some("https://api.github.com/repositories/1/contents?page=2")
+        );
+
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                "<https://api.github.com/x?page=4>; rel=\"prev\", \
+                 <https://api.github.com/x?page=1>; rel=\"first\"",
+            ),
+        );
+        assert_eq!(next_page_url(&headers), None);
+    }
+
+    #[test]
+    fn retry_after_wins_over_reset() {
+        let wait = rate_limited(Some(unix_now() + 30), Some(5));
+        assert_between(rate_limit_wait(&wait), 5, 6);
+    }
+
+    #[test]
+    fn waits_until_the_reset() {
+        assert_between(
+            rate_limit_wait(&rate_limited(Some(unix_now() + 10), None)),
+            10,
+            13,
+        );
+        // Already reset, one second for clock skew
+        assert_between(
+            rate_limit_wait(&rate_limited(Some(unix_now() - 5), None)),
+            1,
+            2,
+        );
+        assert_between(rate_limit_wait(&rate_limited(None, None)), 60, 61);
+    }
+
+    #[test]
+    fn does_not_wait_past_the_limit() {
+        assert_eq!(
+            rate_limit_wait(&rate_limited(Some(unix_now() + 3600), None)),
+            None
+        );
+        assert_eq!(rate_limit_wait(&rate_limited(None, Some(61))), None);
+        assert_eq!(
+            rate_limit_wait(&GitHubError::network("GET /user", "timed out")),
+            None
+        );
+    }
+
+    #[test]
+    fn backoff_stays_within_its_jitter() {
+        for attempt in 1..=3 {
+            let base = BASE_BACKOFF * 2u32.pow(attempt - 1);
+            for _ in 0..20 {
+                let wait = backoff(attempt);
+                assert!(wait >= base && wait < base * 2, "{:?}", wait);
+            }
+        }
+    }
+
+    /// Answers each connection with the next of `responses` (the last one
+    /// repeats) and counts the requests.
+    fn serve(responses: &'static [&'static str]) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/repos/o/r/contents/a.toml",
+            listener.local_addr().unwrap()
+        );
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                }
+                reader.read_exact(&mut vec![0; content_length]).unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let status = responses[n.min(responses.len() - 1)];
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}",
+                    status
+                )
+                .unwrap();
+            }
+        });
+        (url, count)
+    }
+
+    #[tokio::test]
+    async fn writes_are_not_retried_on_5xx() {
+        for method in [Method::POST, Method::PATCH, Method::PUT] {
+            let (url, count) = serve(&["503 Service Unavailable"]);
+            let request = github_request(&Client::new(), method.clone(), &url, "t")
+                .json(&serde_json::json!({ "sha": "abc" }));
+            let error = send_github(request).await.unwrap_err();
+            assert_eq!(error.status(), Some(503));
+            assert_eq!(count.load(Ordering::SeqCst), 1, "{}", method);
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_are_retried_on_5xx() {
+        let (url, count) = serve(&["502 Bad Gateway", "200 OK"]);
+        let response = send_github(github_request(&Client::new(), Method::GET, &url, "t"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,26 +1,69 @@
 use anyhow::{Context, Result};
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
 use git2::{Repository, ResetType, Status};
-use log; // Added for logging
-use reqwest::{
-    header::{HeaderMap, HeaderValue, AUTHORIZATION},
-    Client, Error as ReqwestError, StatusCode,
-};
+use reqwest::{Client, Error as ReqwestError};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 use std::time::{Duration, Instant};
-use std::{env, path::PathBuf};
+use ts_rs::TS;
 
 use std::path::Path;
 
-#[derive(Serialize, Deserialize, Debug)]
+mod articles;
+mod body;
+mod chem;
+mod config;
+mod error;
+mod forge;
+mod gitea;
+mod github_http;
+mod math;
+mod migrations;
+mod reading;
+mod schema;
+mod timestamps;
+mod token_store;
+mod validation;
+pub use articles::{
+    article_slug, delete_article, fetch_remote_article, find_slug_references, list_remote_articles,
+    local_blob_sha, migrate_remote_articles, read_local_article, rename_article,
+    revert_article_change, ArticleChange, ArticleSummary, LoadedArticle, MigrationSummary,
+    RemoteArticles,
+};
+pub use body::{has_unsafe_markup, render_body, render_markdown, sanitize_html, BodyFormat};
+pub use config::{AppConfig, ForgeKind, CONFIG_FILE_NAME};
+pub use error::GitHubError;
+pub use forge::{forge_for_config, Forge, GitHubForge, RepoPermissions, WhoAmI};
+pub use gitea::GiteaForge;
+use github_http::{
+    get_github_pages, github_request, parse_github_json, send_github, send_github_json,
+};
+pub use github_http::{github_rate_limit, RateLimitBudget};
+pub use migrations::{migrate, schema_version, upgrade_article_toml, CURRENT_SCHEMA_VERSION};
+pub use reading::{reading_stats, reading_time_diverges, ReadingStats, WORDS_PER_MINUTE};
+pub use schema::{
+    article_json_schema, generated_files, typescript_definitions, ARTICLE_SCHEMA_PATH,
+    TYPESCRIPT_PATH,
+};
+pub use timestamps::{format_timestamp, parse_timestamp};
+pub use token_store::{TokenBackend, TokenStore};
+pub use validation::{ensure_article_valid, validate, Diagnostic, Severity};
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema, TS)]
 pub struct Image {
     pub url: String,
     pub alt: String,
     pub caption: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, TS)]
 pub struct Author {
     pub name: String,
     #[serde(rename = "authorBio")]
@@ -28,7 +71,7 @@ pub struct Author {
     pub slug: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, TS)]
 pub struct Question {
     pub question: String,
     pub answers: Vec<String>, // MODIFIED: Was Vec<Answer>
@@ -46,7 +89,7 @@ pub struct Question {
 //     }
 // }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, TS)]
 pub struct Professor {
     pub name: String,
     #[serde(rename = "professorBio")]
@@ -54,69 +97,247 @@ pub struct Professor {
     pub slug: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// An article file in the website repo. Dates are read leniently, see
+/// `parse_timestamp`, and written as ISO-8601 in UTC.
+#[derive(Serialize, Deserialize, Debug, JsonSchema, TS)]
 pub struct Article {
+    /// Which version of this shape the file was written in, see `migrate`.
+    #[serde(
+        rename = "schemaVersion",
+        default = "migrations::current_schema_version"
+    )]
+    #[ts(optional, as = "Option<u32>")]
+    pub schema_version: u32,
     pub title: String,
     pub description: String,
     pub body: String,
+    /// HTML when missing.
+    #[serde(rename = "bodyFormat", default)]
+    #[ts(as = "Option<BodyFormat>", optional)]
+    pub body_format: BodyFormat,
+    /// `body` as the website shows it, filled in by `render_body_html`.
+    #[serde(rename = "bodyHtml", default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub body_html: Option<String>,
     pub image: Image,
     pub authors: Vec<Author>,
     pub professor: Professor,
+    #[ts(optional = nullable)]
     pub questions: Option<Vec<Question>>,
-    #[serde(rename = "createdAt")]
-    pub created_at: String,
-    #[serde(rename = "publishedAt")]
-    pub published_at: String,
+    #[serde(rename = "createdAt", with = "timestamps::timestamp")]
+    #[schemars(with = "String")]
+    #[ts(as = "String")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "publishedAt", with = "timestamps::timestamp")]
+    #[schemars(with = "String")]
+    #[ts(as = "String")]
+    pub published_at: DateTime<Utc>,
+    /// In minutes.
     #[serde(rename = "readingTime")]
     pub reading_time: u32,
-    #[serde(rename = "updatedAt")]
-    pub updated_at: Option<String>,
-    #[serde(rename = "lastUpdatedAt")]
-    pub last_updated_at: Option<String>,
+    #[serde(rename = "updatedAt", default, with = "timestamps::optional_timestamp")]
+    #[schemars(with = "Option<String>")]
+    #[ts(as = "Option<String>", optional = nullable)]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(
+        rename = "lastUpdatedAt",
+        default,
+        with = "timestamps::optional_timestamp"
+    )]
+    #[schemars(with = "Option<String>")]
+    #[ts(as = "Option<String>", optional = nullable)]
+    pub last_updated_at: Option<DateTime<Utc>>,
     pub category: String, // ADDED category field
 }
 
+impl Article {
+    /// Renders and sanitizes the body into `bodyHtml`, done before upload. An
+    /// HTML body is replaced by its sanitized version too, the website reads
+    /// it directly. Formulas stay as source there, MathML only goes into
+    /// `bodyHtml`.
+    pub fn render_body_html(&mut self) {
+        self.body_html = Some(render_body(&self.body, self.body_format));
+        if self.body_format == BodyFormat::Html {
+            self.body = sanitize_html(&self.body);
+        }
+    }
+
+    /// Word count and reading time of the body.
+    pub fn reading_stats(&self) -> ReadingStats {
+        reading_stats(&self.body, self.body_format)
+    }
+
+    /// Replaces `readingTime` with the one computed from the body, warning
+    /// when the hand-entered value was far off.
+    pub fn update_reading_time(&mut self) -> ReadingStats {
+        let stats = self.reading_stats();
+        if reading_time_diverges(self.reading_time, stats.minutes) {
+            log::warn!(
+                "'{}' said {} min to read, the body reads in about {}, using that",
+                self.title,
+                self.reading_time,
+                stats.minutes
+            );
+        }
+        self.reading_time = stats.minutes;
+        stats
+    }
+
+    /// Stamps `updatedAt` and `lastUpdatedAt` with the current time, done
+    /// when an existing article is uploaded again.
+    pub fn mark_updated(&mut self) {
+        let now = timestamps::now();
+        self.updated_at = Some(now);
+        self.last_updated_at = Some(now);
+    }
+}
+
 pub fn get_article_from_toml_file(file_path: &Path) -> Result<Article> {
     let toml_content = fs::read_to_string(file_path)
         .with_context(|| format!("Failed to read the file: {}", file_path.to_str().unwrap()))?;
-    let article: Article = toml::from_str(&toml_content)
+    article_from_toml_str(&toml_content)
+}
+
+/// Parses an article file, migrating it first if it is from an older
+/// `schemaVersion`.
+pub fn article_from_toml_str(toml_content: &str) -> Result<Article> {
+    let mut document: toml::Table = toml::from_str(toml_content)
+        .with_context(|| "Failed to parse the TOML content into an Article struct")?;
+    migrate(&mut document)?;
+    let article: Article = document
+        .try_into()
         .with_context(|| "Failed to parse the TOML content into an Article struct")?;
     Ok(article)
 }
 
-#[derive(Deserialize, Debug)]
+/// What a repo entry is. Tree listings say blob/tree/commit, the Contents
+/// API file/dir/submodule, both parse to the same variants.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GitHubEntryKind {
+    #[serde(alias = "blob")]
+    File,
+    #[serde(alias = "tree")]
+    Dir,
+    Symlink,
+    #[serde(alias = "commit")]
+    Submodule,
+}
+
+/// A file or directory in the repo on the configured branch.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GitHubFile {
     pub name: String,
+    /// Relative to the repo root.
     pub path: String,
-    pub r#type: String,
+    pub r#type: GitHubEntryKind,
+    /// Blob SHA, what `expected_sha` wants when updating the file.
+    pub sha: String,
+    /// In bytes, 0 for directories.
+    #[serde(default)]
+    pub size: u64,
+    /// Raw file URL. `None` for directories, and tree listings don't have it.
+    #[serde(default)]
+    pub download_url: Option<String>,
 }
 
-pub fn list_files_in_github_path(
-    repo_name_with_owner: &str, // Changed to "owner/repo" format
-    github_path: &str,
-) -> Result<Vec<GitHubFile>, Box<dyn std::error::Error>> {
-    let github_token = env::var("GITHUB_TOKEN").expect("GITHUB_TOKEN not set");
-    let api_url = format!(
-        "https://api.github.com/repos/{}/contents/{}",
-        repo_name_with_owner, github_path
-    );
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("token {}", github_token))?,
-    );
-    headers.insert("User-Agent", HeaderValue::from_static("rust-client"));
+#[derive(Deserialize, Debug)]
+pub(crate) struct GitTreeEntry {
+    path: String,
+    /// Git file mode, `100644` for a regular file, `100755` executable.
+    #[serde(default)]
+    mode: String,
+    r#type: GitHubEntryKind,
+    sha: String,
+    #[serde(default)]
+    size: u64,
+}
 
-    let client = reqwest::blocking::Client::new();
-    let response = client.get(&api_url).headers(headers).send()?;
+impl GitTreeEntry {
+    /// `base` is the repo path of the tree the entry was listed from.
+    pub(crate) fn into_file(self, base: &str) -> GitHubFile {
+        let path = if base.is_empty() {
+            self.path
+        } else {
+            format!("{}/{}", base, self.path)
+        };
+        GitHubFile {
+            name: path.rsplit('/').next().unwrap_or_default().to_string(),
+            path,
+            r#type: self.r#type,
+            sha: self.sha,
+            size: self.size,
+            download_url: None,
+        }
+    }
+}
 
-    if response.status().is_success() {
-        let files: Vec<GitHubFile> = response.json()?;
-        Ok(files)
+#[derive(Deserialize, Debug)]
+pub(crate) struct GitTreeResponse {
+    pub(crate) tree: Vec<GitTreeEntry>,
+    #[serde(default)]
+    pub(crate) truncated: bool,
+}
+
+/// Lists one directory on the configured branch through the Contents API.
+pub async fn list_files_in_github_path(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    github_path: &str,
+) -> Result<Vec<GitHubFile>, GitHubError> {
+    let api_url = format!("{}/contents/{}", config.repo_api_url(), github_path);
+    get_github_pages(client, &api_url, token, &[("ref", &config.branch)]).await
+}
+
+/// Lists everything below `github_path` on the configured branch, files and
+/// directories, in one Git Trees API request.
+///
+/// GitHub cuts trees off at 100,000 entries or 7 MB. When that happens the
+/// directory is walked with the Contents API instead, one request per
+/// directory.
+pub async fn list_github_tree(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    github_path: &str,
+) -> Result<Vec<GitHubFile>, GitHubError> {
+    let github_path = github_path.trim_matches('/');
+    // `<ref>:<path>` names the subtree without looking up its SHA first
+    let tree_ish = if github_path.is_empty() {
+        config.branch.clone()
     } else {
-        let error_message = response.text()?;
-        Err(format!("Failed to list files: {}", error_message).into())
+        format!("{}:{}", config.branch, github_path)
+    };
+    let api_url = format!("{}/git/trees/{}", config.repo_api_url(), tree_ish);
+    let response: GitTreeResponse = send_github_json(
+        github_request(client, reqwest::Method::GET, &api_url, token).query(&[("recursive", "1")]),
+    )
+    .await?;
+
+    if response.truncated {
+        log::warn!(
+            "Tree of {} is truncated, listing it directory by directory",
+            tree_ish
+        );
+        let mut entries = Vec::new();
+        let mut pending = vec![github_path.to_string()];
+        while let Some(dir) = pending.pop() {
+            for entry in list_files_in_github_path(client, config, token, &dir).await? {
+                if entry.r#type == GitHubEntryKind::Dir {
+                    pending.push(entry.path.clone());
+                }
+                entries.push(entry);
+            }
+        }
+        return Ok(entries);
     }
+
+    Ok(response
+        .tree
+        .into_iter()
+        .map(|entry| entry.into_file(github_path))
+        .collect())
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -128,24 +349,27 @@ pub struct GithubDeviceCodeResponse {
     pub interval: u32,
 }
 
-pub fn start_git_auth() -> Result<GithubDeviceCodeResponse> {
-    let client = reqwest::blocking::Client::new();
+pub async fn start_git_auth(
+    client: &Client,
+    config: &AppConfig,
+) -> Result<GithubDeviceCodeResponse> {
     let params = [
-        ("client_id", "Ov23liELuI9vWwtgYC5f"),
+        ("client_id", config.client_id.as_str()),
         ("scope", "repo user"),
     ];
 
     let response = client
-        .post("https://github.com/login/device/code")
+        .post(format!("{}/login/device/code", config.web_base_url))
         .header("Accept", "application/json")
         .form(&params)
-        .send()?;
+        .send()
+        .await?;
 
     if response.status().is_success() {
-        let device_code_response: GithubDeviceCodeResponse = response.json()?;
+        let device_code_response: GithubDeviceCodeResponse = response.json().await?;
         Ok(device_code_response)
     } else {
-        let error_message = response.text()?;
+        let error_message = response.text().await?;
         Err(anyhow::anyhow!(
             "Failed to get device code. Error: {}",
             error_message
@@ -163,6 +387,7 @@ pub enum OAuthError {
     IncorrectDeviceCode,
     AccessDenied,
     DeviceFlowDisabled,
+    Cancelled,
     Unknown(String),
 }
 
@@ -178,15 +403,17 @@ pub struct OAuthResponse {
 #[derive(Debug)]
 pub struct GitHubConfig {
     pub client_id: String,
+    pub web_base_url: String,
     pub device_code: String,
     pub interval: u64,
     pub expires_in: u32,
 }
 
 impl GitHubConfig {
-    pub fn new(device_code_response: GithubDeviceCodeResponse) -> Self {
+    pub fn new(app_config: &AppConfig, device_code_response: GithubDeviceCodeResponse) -> Self {
         GitHubConfig {
-            client_id: "Ov23liELuI9vWwtgYC5f".to_string(),
+            client_id: app_config.client_id.clone(),
+            web_base_url: app_config.web_base_url.clone(),
             device_code: device_code_response.device_code,
             interval: device_code_response.interval.max(5) as u64,
             expires_in: device_code_response.expires_in,
@@ -194,15 +421,97 @@ impl GitHubConfig {
     }
 }
 
-pub async fn wait_for_github(config: GitHubConfig) -> Result<String, OAuthError> {
-    let client = Client::new();
-    let url = "https://github.com/login/oauth/access_token";
+/// State of the device flow polling, reported before every wait.
+#[derive(Serialize, Debug, Clone)]
+pub struct AuthPollProgress {
+    pub attempt: u32,
+    /// Seconds until the user code expires.
+    pub remaining_secs: u64,
+    /// Seconds until the next poll, grows when GitHub answers `slow_down`.
+    pub interval_secs: u64,
+}
+
+/// Reports device flow polling progress and lets another task stop the
+/// polling. Clones share the same cancellation state.
+#[derive(Clone, Default)]
+pub struct AuthPollHandler {
+    callback: Option<Arc<dyn Fn(AuthPollProgress) + Send + Sync>>,
+    cancelled: Arc<AtomicBool>,
+    wake: Arc<tokio::sync::Notify>,
+}
+
+impl AuthPollHandler {
+    pub fn new(callback: impl Fn(AuthPollProgress) + Send + Sync + 'static) -> Self {
+        AuthPollHandler {
+            callback: Some(Arc::new(callback)),
+            ..Default::default()
+        }
+    }
+
+    /// Stops the polling, also while it is waiting between polls.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.wake.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Whether `other` is a clone of this handler.
+    pub fn is_same(&self, other: &AuthPollHandler) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+
+    fn report(&self, progress: AuthPollProgress) {
+        if let Some(callback) = &self.callback {
+            callback(progress);
+        }
+    }
+
+    /// Sleeps for `duration`, returning early with `Cancelled` on `cancel()`.
+    async fn sleep(&self, duration: Duration) -> Result<(), OAuthError> {
+        let woken = self.wake.notified();
+        tokio::pin!(woken);
+        // Register before checking the flag so a cancel in between isn't lost
+        woken.as_mut().enable();
+        if self.is_cancelled() {
+            return Err(OAuthError::Cancelled);
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => Ok(()),
+            _ = woken => Err(OAuthError::Cancelled),
+        }
+    }
+}
+
+impl std::fmt::Debug for AuthPollHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthPollHandler")
+            .field("has_callback", &self.callback.is_some())
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+pub async fn wait_for_github(
+    client: &Client,
+    config: GitHubConfig,
+    handler: &AuthPollHandler,
+) -> Result<String, OAuthError> {
+    let url = format!("{}/login/oauth/access_token", config.web_base_url);
     let mut current_interval_secs = config.interval;
     let deadline = Instant::now() + Duration::from_secs(config.expires_in as u64);
+    let mut attempt = 0;
 
     while Instant::now() < deadline {
+        if handler.is_cancelled() {
+            log::info!("Device flow polling cancelled");
+            return Err(OAuthError::Cancelled);
+        }
+        attempt += 1;
         let response_result = client
-            .post(url)
+            .post(&url)
             .header("Accept", "application/json")
             .form(&[
                 ("client_id", &config.client_id),
@@ -226,7 +535,14 @@ pub async fn wait_for_github(config: GitHubConfig) -> Result<String, OAuthError>
             Err(err) => return Err(err),
         }
 
-        tokio::time::sleep(Duration::from_secs(current_interval_secs)).await;
+        handler.report(AuthPollProgress {
+            attempt,
+            remaining_secs: deadline.saturating_duration_since(Instant::now()).as_secs(),
+            interval_secs: current_interval_secs,
+        });
+        handler
+            .sleep(Duration::from_secs(current_interval_secs))
+            .await?;
     }
 
     Err(OAuthError::ExpiredToken)
@@ -282,11 +598,133 @@ async fn handle_response(
     }
 }
 
-pub fn ensure_repo_is_healthy(url: &str, path: &Path) -> Result<Repository> {
+/// Progress reported while cloning, fetching or checking out the website repo.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum GitProgress {
+    Transfer {
+        received_objects: usize,
+        total_objects: usize,
+        indexed_objects: usize,
+        received_bytes: usize,
+        indexed_deltas: usize,
+        total_deltas: usize,
+    },
+    Checkout {
+        path: Option<String>,
+        completed: usize,
+        total: usize,
+    },
+    Push {
+        current: usize,
+        total: usize,
+        bytes: usize,
+    },
+}
+
+/// Forwards git2 progress to a callback and lets another thread abort the
+/// transfer. Clones share the same cancellation flag.
+#[derive(Clone, Default)]
+pub struct GitProgressHandler {
+    callback: Option<Arc<dyn Fn(GitProgress) + Send + Sync>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl GitProgressHandler {
+    pub fn new(callback: impl Fn(GitProgress) + Send + Sync + 'static) -> Self {
+        GitProgressHandler {
+            callback: Some(Arc::new(callback)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn report(&self, progress: GitProgress) {
+        if let Some(callback) = &self.callback {
+            callback(progress);
+        }
+    }
+
+    fn remote_callbacks(&self) -> git2::RemoteCallbacks<'_> {
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.transfer_progress(move |stats| {
+            self.report(GitProgress::Transfer {
+                received_objects: stats.received_objects(),
+                total_objects: stats.total_objects(),
+                indexed_objects: stats.indexed_objects(),
+                received_bytes: stats.received_bytes(),
+                indexed_deltas: stats.indexed_deltas(),
+                total_deltas: stats.total_deltas(),
+            });
+            // Returning false makes libgit2 abort the transfer with GIT_EUSER
+            !self.is_cancelled()
+        });
+        callbacks
+    }
+
+    fn checkout_builder(&self) -> git2::build::CheckoutBuilder<'_> {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.progress(move |path, completed, total| {
+            self.report(GitProgress::Checkout {
+                path: path.map(|p| p.to_string_lossy().into_owned()),
+                completed,
+                total,
+            });
+        });
+        checkout
+    }
+}
+
+impl std::fmt::Debug for GitProgressHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GitProgressHandler")
+            .field("has_callback", &self.callback.is_some())
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+fn clone_with_progress(
+    url: &str,
+    path: &Path,
+    progress: &GitProgressHandler,
+) -> Result<Repository> {
+    let mut fetch_opts = git2::FetchOptions::new();
+    fetch_opts.remote_callbacks(progress.remote_callbacks());
+    let cloned = git2::build::RepoBuilder::new()
+        .fetch_options(fetch_opts)
+        .with_checkout(progress.checkout_builder())
+        .clone(url, path);
+    match cloned {
+        Ok(repo) => Ok(repo),
+        Err(e) => {
+            // Don't leave a half cloned repo behind for the next sync to trip over
+            let _ = fs::remove_dir_all(path);
+            if progress.is_cancelled() {
+                Err(anyhow::anyhow!("Clone of {} was cancelled", url))
+            } else {
+                Err(e).with_context(|| format!("Cant Clone {} into dir :(", url))
+            }
+        }
+    }
+}
+
+pub fn ensure_repo_is_healthy(
+    url: &str,
+    path: &Path,
+    progress: &GitProgressHandler,
+) -> Result<Repository> {
     match path.exists() {
         true => match path.is_dir() {
             true => match Repository::open(path) {
-                Ok(x) => return Ok(x),
+                Ok(x) => Ok(x),
                 Err(_) => {
                     fs::remove_dir_all(path)
                         .context("Could not delete dir")
@@ -294,7 +732,7 @@ pub fn ensure_repo_is_healthy(url: &str, path: &Path) -> Result<Repository> {
                     fs::create_dir(path)
                         .context("Could not create dir")
                         .unwrap();
-                    Repository::clone(url, path).context("Cant Clone Repo into dir :(")
+                    clone_with_progress(url, path, progress)
                 }
             },
             false => Err(anyhow::anyhow!(
@@ -305,27 +743,64 @@ pub fn ensure_repo_is_healthy(url: &str, path: &Path) -> Result<Repository> {
             fs::create_dir_all(path)
                 .with_context(|| "Cant Make Dir :(")
                 .unwrap();
-            Repository::clone(url, path).with_context(|| "Cant Clone into dir :(")
+            clone_with_progress(url, path, progress)
         }
     }
 }
-pub fn update_repo_or_reset(repo: &mut Repository) -> Result<&mut Repository, git2::Error> {
+fn fetch_origin_branch<'r>(
+    repo: &'r Repository,
+    branch: &str,
+    progress: &GitProgressHandler,
+) -> Result<git2::Commit<'r>, git2::Error> {
+    let remote_name = "origin";
+    let mut remote = repo.find_remote(remote_name)?;
+    let mut fetch_opts = git2::FetchOptions::new();
+    fetch_opts.remote_callbacks(progress.remote_callbacks());
+    remote.fetch(
+        &[format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch)],
+        Some(&mut fetch_opts),
+        None,
+    )?;
+    let remote_head = repo.find_reference(&format!("refs/remotes/origin/{}", branch))?;
+    remote_head.peel_to_commit()
+}
+
+/// Points HEAD at the local `branch`, creating it at `start` if the clone
+/// doesn't have it yet (e.g. the configured branch isn't the default one).
+/// The working tree is left alone, callers reset it right after.
+fn switch_to_branch(
+    repo: &Repository,
+    branch: &str,
+    start: &git2::Commit,
+) -> Result<(), git2::Error> {
+    let ref_name = format!("refs/heads/{}", branch);
+    if repo.head()?.name() == Some(ref_name.as_str()) {
+        return Ok(());
+    }
+    if repo.find_branch(branch, git2::BranchType::Local).is_err() {
+        repo.branch(branch, start, false)?;
+    }
+    repo.set_head(&ref_name)?;
+    log::info!("Switched to branch {}", branch);
+    Ok(())
+}
+
+pub fn update_repo_or_reset<'a>(
+    repo: &'a mut Repository,
+    branch: &str,
+    progress: &GitProgressHandler,
+) -> Result<&'a mut Repository, git2::Error> {
     {
-        let remote_name = "origin";
-        let mut remote = repo.find_remote(remote_name)?;
-        let mut fetch_opts = git2::FetchOptions::new();
-        let callbacks = git2::RemoteCallbacks::new();
-        fetch_opts.remote_callbacks(callbacks);
-        remote.fetch(
-            &["refs/heads/main:refs/heads/main"],
-            Some(&mut fetch_opts),
-            None,
+        let remote_commit = fetch_origin_branch(repo, branch, progress)?;
+        switch_to_branch(repo, branch, &remote_commit)?;
+        repo.reset(
+            remote_commit.as_object(),
+            ResetType::Hard,
+            Some(&mut progress.checkout_builder()),
         )?;
-        let remote_head = repo.find_reference("refs/remotes/origin/main")?;
-        let remote_commit = remote_head.peel_to_commit()?;
-        repo.reset(&remote_commit.as_object(), ResetType::Hard, None)?;
-        println!(
-            "Repository has been reset to the state of origin/main, discarding all local changes."
+        log::info!(
+            "Repository has been reset to the state of origin/{}, discarding all local changes.",
+            branch
         );
     }
     Ok(repo)
@@ -353,12 +828,367 @@ pub fn get_changed_articles(repo: &mut Repository) -> Result<Vec<PathBuf>, git2:
         .collect()
 }
 
+/// What `update_repo_preserving_changes` did with the local work it found.
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct SyncReport {
+    /// Branch holding a snapshot of the local edits (and any unpushed
+    /// commits) taken before the branch was moved, if there was anything to keep.
+    pub draft_branch: Option<String>,
+    /// Files reapplied cleanly on top of the new branch tip.
+    pub reapplied: Vec<PathBuf>,
+    /// Files that changed both locally and upstream. They are left in the
+    /// working tree with conflict markers, the draft branch has the original.
+    pub conflicts: Vec<PathBuf>,
+}
+
+fn local_signature(repo: &Repository) -> Result<git2::Signature<'static>, git2::Error> {
+    repo.signature()
+        .or_else(|_| git2::Signature::now("Professor App", "app@thebeakers.com"))
+}
+
+fn create_draft_branch<'r>(
+    repo: &'r Repository,
+    commit: &git2::Commit<'r>,
+) -> Result<String, git2::Error> {
+    let base_name = format!("drafts/{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    let mut name = base_name.clone();
+    let mut suffix = 1;
+    while repo.find_branch(&name, git2::BranchType::Local).is_ok() {
+        suffix += 1;
+        name = format!("{}-{}", base_name, suffix);
+    }
+    repo.branch(&name, commit, false)?;
+    Ok(name)
+}
+
+/// Commits the given working tree paths on top of `head_commit`. HEAD is only
+/// moved when `update_ref` is given.
+fn commit_local_changes<'r>(
+    repo: &'r Repository,
+    head_commit: &git2::Commit<'r>,
+    changed: &[PathBuf],
+    signature: &git2::Signature,
+    message: &str,
+    update_ref: Option<&str>,
+) -> Result<git2::Commit<'r>, git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+    let mut index = repo.index()?;
+    for path in changed {
+        if workdir.join(path).exists() {
+            index.add_path(path)?;
+        } else {
+            index.remove_path(path)?;
+        }
+    }
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let oid = repo.commit(
+        update_ref,
+        signature,
+        signature,
+        message,
+        &tree,
+        &[head_commit],
+    )?;
+    repo.find_commit(oid)
+}
+
+fn index_entry_path(entry: &git2::IndexEntry) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned())
+}
+
+/// Brings `branch` up to its origin counterpart without throwing away local
+/// work.
+///
+/// Local edits are committed to a `drafts/...` branch, `branch` is moved to
+/// the remote tip, and the edits are then cherry-picked back into the working
+/// tree (unstaged). Files that conflict get merge markers and are listed in
+/// the report instead of being overwritten.
+pub fn update_repo_preserving_changes(
+    repo: &mut Repository,
+    branch: &str,
+    progress: &GitProgressHandler,
+) -> Result<SyncReport, git2::Error> {
+    let changed = get_changed_articles(repo)?;
+    let remote_commit = fetch_origin_branch(repo, branch, progress)?;
+    switch_to_branch(repo, branch, &remote_commit)?;
+    let head_commit = repo.head()?.peel_to_commit()?;
+    let mut report = SyncReport::default();
+
+    let draft_commit = if changed.is_empty() {
+        None
+    } else {
+        let message = format!(
+            "Draft: {} local article change(s) saved before sync",
+            changed.len()
+        );
+        Some(commit_local_changes(
+            repo,
+            &head_commit,
+            &changed,
+            &local_signature(repo)?,
+            &message,
+            None,
+        )?)
+    };
+    let has_unpushed_commits = head_commit.id() != remote_commit.id()
+        && !repo.graph_descendant_of(remote_commit.id(), head_commit.id())?;
+    if let Some(draft_commit) = &draft_commit {
+        report.draft_branch = Some(create_draft_branch(repo, draft_commit)?);
+    } else if has_unpushed_commits {
+        report.draft_branch = Some(create_draft_branch(repo, &head_commit)?);
+    }
+    if let Some(draft_branch) = &report.draft_branch {
+        log::info!("Saved local work to branch {}", draft_branch);
+    }
+
+    repo.reset(
+        remote_commit.as_object(),
+        ResetType::Hard,
+        Some(&mut progress.checkout_builder()),
+    )?;
+
+    if let Some(draft_commit) = draft_commit {
+        let mut merged = repo.cherrypick_commit(&draft_commit, &remote_commit, 0, None)?;
+        if merged.has_conflicts() {
+            for conflict in merged.conflicts()? {
+                let conflict = conflict?;
+                if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+                    report.conflicts.push(index_entry_path(&entry));
+                }
+            }
+        }
+        report.reapplied = changed
+            .into_iter()
+            .filter(|path| !report.conflicts.contains(path))
+            .collect();
+
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout
+            .force()
+            .allow_conflicts(true)
+            .conflict_style_merge(true)
+            .our_label(&format!("origin/{}", branch))
+            .their_label("local edits");
+        repo.checkout_index(Some(&mut merged), Some(&mut checkout))?;
+        // checkout_index leaves the cherry-picked entries staged, keep them as
+        // plain working tree edits like they were before the sync
+        let mut index = repo.index()?;
+        index.read_tree(&remote_commit.tree()?)?;
+        index.write()?;
+    }
+
+    log::info!(
+        "Synced with origin/{}: {} file(s) reapplied, {} conflict(s)",
+        branch,
+        report.reapplied.len(),
+        report.conflicts.len()
+    );
+    Ok(report)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PublishResult {
+    pub commit_sha: String,
+    pub files: Vec<PathBuf>,
+}
+
+/// Files among `paths` that still have the conflict markers a sync leaves.
+fn files_with_conflict_markers(workdir: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter(|path| {
+            let Ok(content) = fs::read_to_string(workdir.join(path)) else {
+                return false;
+            };
+            let has_line = |marker: &str| content.lines().any(|line| line.starts_with(marker));
+            has_line("<<<<<<< ") && has_line("=======") && has_line(">>>>>>> ")
+        })
+        .cloned()
+        .collect()
+}
+
+/// Commits every changed article in the working tree as one commit on
+/// `branch` and pushes it to origin. `token` is sent as HTTPS credentials when the
+/// remote asks for them, local remotes never do.
+///
+/// Refuses while a file still has conflict markers from
+/// `update_repo_preserving_changes`. If the push fails the commit is undone
+/// again so the edits are back to being plain working tree changes.
+pub fn publish_local_changes(
+    repo: &mut Repository,
+    branch: &str,
+    author: &CommitAuthor,
+    message: &str,
+    token: Option<&str>,
+    progress: &GitProgressHandler,
+) -> Result<PublishResult, git2::Error> {
+    let changed = get_changed_articles(repo)?;
+    if changed.is_empty() {
+        return Err(git2::Error::from_str(
+            "There are no local changes to publish",
+        ));
+    }
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+    let conflicted = files_with_conflict_markers(workdir, &changed);
+    if !conflicted.is_empty() {
+        let names: Vec<String> = conflicted.iter().map(|p| p.display().to_string()).collect();
+        return Err(git2::Error::from_str(&format!(
+            "Resolve the conflicts from the last sync in {} before publishing",
+            names.join(", ")
+        )));
+    }
+
+    let head_commit = repo.head()?.peel_to_commit()?;
+    let signature = git2::Signature::now(&author.name, &author.email)?;
+    let commit = commit_local_changes(
+        repo,
+        &head_commit,
+        &changed,
+        &signature,
+        message,
+        Some("HEAD"),
+    )?;
+    let mut index = repo.index()?;
+    index.read_tree(&commit.tree()?)?;
+    index.write()?;
+    log::info!(
+        "Committed {} file(s) as {}, pushing to origin",
+        changed.len(),
+        commit.id()
+    );
+
+    if let Err(e) = push_branch(repo, branch, token, progress) {
+        log::error!("Push failed, undoing local commit {}: {}", commit.id(), e);
+        repo.reset(head_commit.as_object(), ResetType::Mixed, None)?;
+        return Err(e);
+    }
+
+    Ok(PublishResult {
+        commit_sha: commit.id().to_string(),
+        files: changed,
+    })
+}
+
+fn push_branch(
+    repo: &Repository,
+    branch: &str,
+    token: Option<&str>,
+    progress: &GitProgressHandler,
+) -> Result<(), git2::Error> {
+    let mut remote = repo.find_remote("origin")?;
+    let rejection = std::cell::RefCell::new(None);
+
+    let mut callbacks = progress.remote_callbacks();
+    if let Some(token) = token {
+        callbacks.credentials(move |_url, _username, _allowed| {
+            git2::Cred::userpass_plaintext("x-access-token", token)
+        });
+    }
+    callbacks.push_transfer_progress(|current, total, bytes| {
+        progress.report(GitProgress::Push {
+            current,
+            total,
+            bytes,
+        });
+    });
+    // Servers report rejected refs here rather than failing the push itself
+    callbacks.push_update_reference(|refname, status| {
+        if let Some(status) = status {
+            *rejection.borrow_mut() = Some(format!("{} was rejected: {}", refname, status));
+        }
+        Ok(())
+    });
+
+    let mut push_opts = git2::PushOptions::new();
+    push_opts.remote_callbacks(callbacks);
+    remote.push(
+        &[format!("refs/heads/{0}:refs/heads/{0}", branch)],
+        Some(&mut push_opts),
+    )?;
+    drop(push_opts);
+
+    match rejection.into_inner() {
+        Some(reason) => Err(git2::Error::from_str(&format!(
+            "{}. Sync with origin before publishing again.",
+            reason
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct LocalArticlesSummary {
+    pub repo_path: PathBuf,
+    pub articles: Vec<ArticleSummary>,
+    pub unreadable: Vec<PathBuf>, // TOML files that did not parse into an Article
+}
+
+/// `path` with `/` separators, as the forge spells repo paths.
+fn repo_path_string(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn find_local_articles(repo_path: &Path, articles_dir: &str) -> Result<LocalArticlesSummary> {
+    let pattern = repo_path.join(articles_dir).join("**").join("*.toml");
+    let pattern = pattern
+        .to_str()
+        .with_context(|| format!("Articles path is not valid UTF-8: {:?}", pattern))?;
+
+    let repo = Repository::open(repo_path)
+        .with_context(|| format!("{:?} is not a git repo", repo_path))?;
+    let mut summary = LocalArticlesSummary {
+        repo_path: repo_path.to_path_buf(),
+        ..Default::default()
+    };
+    for entry in glob::glob(pattern).context("Invalid articles glob pattern")? {
+        let file_path = entry.context("Could not read an entry in the articles dir")?;
+        let relative_path = file_path
+            .strip_prefix(repo_path)
+            .unwrap_or(&file_path)
+            .to_path_buf();
+        let loaded = get_article_from_toml_file(&file_path)
+            .and_then(|article| Ok((article, local_blob_sha(&repo, &relative_path)?)));
+        match loaded {
+            Ok((article, sha)) => summary.articles.push(ArticleSummary::new(
+                repo_path_string(&relative_path),
+                sha.unwrap_or_default(),
+                &article,
+            )),
+            Err(e) => {
+                log::warn!("Skipping unreadable article {:?}: {:#}", file_path, e);
+                summary.unreadable.push(relative_path);
+            }
+        }
+    }
+    log::info!(
+        "Found {} local articles ({} unreadable) in {:?}",
+        summary.articles.len(),
+        summary.unreadable.len(),
+        repo_path
+    );
+    Ok(summary)
+}
+
 // Structs for GitHub API interaction (file upload)
 #[derive(Serialize, Debug)]
 struct GitHubPutFileRequest<'a> {
     message: &'a str,
     content: String, // Base64 encoded
     sha: Option<String>,
+    branch: &'a str,
     committer: Committer<'a>,
 }
 
@@ -371,11 +1201,7 @@ struct Committer<'a> {
 #[derive(Deserialize, Debug)]
 struct GitHubFileGetResponse {
     sha: String,
-}
-
-#[derive(Deserialize, Debug)]
-struct GitHubErrorResponse {
-    message: String,
+    content: Option<String>, // Base64 encoded, wrapped with newlines
 }
 
 #[derive(Deserialize, Debug)]
@@ -386,96 +1212,123 @@ struct GitHubPutFileResponse {
 #[derive(Deserialize, Debug)]
 struct GitHubCommitInfo {
     sha: String,
-    message: String,
 }
 
-pub async fn upload_file_to_github(
+/// A file as it currently is on GitHub.
+#[derive(Serialize, Debug, Clone)]
+pub struct RemoteFile {
+    pub sha: String,
+    pub content: String,
+}
+
+/// Fetches a file from the configured branch through the Contents API,
+/// `None` if it does not exist.
+pub async fn get_github_file(
+    client: &Client,
+    config: &AppConfig,
     token: &str,
-    owner: &str,
-    repo: &str,
     path_in_repo: &str,
-    commit_message: &str,
-    file_content: &str,
-) -> Result<String, String> {
-    let client = Client::new();
-    let api_url = format!(
-        "https://api.github.com/repos/{}/{}/contents/{}",
-        owner, repo, path_in_repo
-    );
+) -> Result<Option<RemoteFile>, GitHubError> {
+    get_github_file_at(client, config, token, path_in_repo, &config.branch).await
+}
 
-    log::debug!("Attempting to GET file info from: {}", api_url);
-    let get_response = client
-        .get(&api_url)
-        .header(AUTHORIZATION, format!("Bearer {}", token))
-        .header("Accept", "application/vnd.github+json")
-        .header("X-GitHub-Api-Version", "2022-11-28")
-        .header("User-Agent", "TauriProfessorApp/0.1.0")
-        .send()
-        .await;
+/// `get_github_file` at `git_ref`, a branch or commit SHA.
+pub async fn get_github_file_at(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    path_in_repo: &str,
+    git_ref: &str,
+) -> Result<Option<RemoteFile>, GitHubError> {
+    let api_url = format!("{}/contents/{}", config.repo_api_url(), path_in_repo);
+    let resp = match send_github(
+        github_request(client, reqwest::Method::GET, &api_url, token).query(&[("ref", git_ref)]),
+    )
+    .await
+    {
+        Ok(resp) => resp,
+        Err(GitHubError::NotFound { .. }) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let request_path = resp.url().path().to_string();
+    let file_data: GitHubFileGetResponse = parse_github_json(resp).await?;
+    // GitHub wraps the base64 payload at 60 columns
+    let encoded: String = file_data
+        .content
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let bytes = BASE64_STANDARD
+        .decode(encoded)
+        .map_err(|e| GitHubError::invalid_response(&request_path, e))?;
+    Ok(Some(RemoteFile {
+        sha: file_data.sha,
+        content: String::from_utf8_lossy(&bytes).into_owned(),
+    }))
+}
 
-    let mut current_sha: Option<String> = None;
+/// Returns `GitHubError::Conflict` if the file on GitHub is no longer the
+/// version with `expected_sha` (`None` meaning it should not exist yet).
+pub async fn ensure_github_file_unchanged(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    path_in_repo: &str,
+    expected_sha: Option<&str>,
+) -> Result<(), GitHubError> {
+    let remote = get_github_file(client, config, token, path_in_repo).await?;
+    conflict_if_changed(remote, path_in_repo, expected_sha)
+}
 
-    match get_response {
-        Ok(resp) => {
-            log::debug!(
-                "GET response status for {}: {}",
-                path_in_repo,
-                resp.status()
-            );
-            if resp.status() == StatusCode::OK {
-                match resp.json::<GitHubFileGetResponse>().await {
-                    Ok(file_data) => {
-                        current_sha = Some(file_data.sha);
-                        log::info!(
-                            "File {} exists, SHA: {}",
-                            path_in_repo,
-                            current_sha.as_ref().unwrap()
-                        );
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "Failed to parse GET response for {}: {}. Assuming new file.",
-                            path_in_repo,
-                            e
-                        );
-                    }
-                }
-            } else if resp.status() == StatusCode::NOT_FOUND {
-                log::info!("File {} not found. Will create a new file.", path_in_repo);
-            } else {
-                let status = resp.status();
-                let error_text = resp
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Unknown error reading response body".into());
-                log::error!(
-                    "Failed to get file info for {}: {} - {}",
-                    path_in_repo,
-                    status,
-                    error_text
-                );
-                return Err(format!(
-                    "GitHub API error (GET {}): {} - {}",
-                    path_in_repo, status, error_text
-                ));
-            }
-        }
-        Err(e) => {
-            log::error!(
-                "Network error while getting file info for {}: {}",
+pub(crate) fn conflict_if_changed(
+    remote: Option<RemoteFile>,
+    path_in_repo: &str,
+    expected_sha: Option<&str>,
+) -> Result<(), GitHubError> {
+    match remote {
+        Some(remote) if Some(remote.sha.as_str()) != expected_sha => {
+            log::warn!(
+                "Conflict on {}: expected {:?}, remote is {}",
                 path_in_repo,
-                e
+                expected_sha,
+                remote.sha
             );
-            return Err(format!("Network error (GET {}): {}", path_in_repo, e));
+            Err(GitHubError::Conflict {
+                path: path_in_repo.to_string(),
+                expected_sha: expected_sha.map(str::to_string),
+                remote_sha: remote.sha,
+                remote_content: remote.content,
+            })
         }
+        _ => Ok(()),
     }
+}
+
+/// Creates or updates a file through the Contents API.
+///
+/// `expected_sha` is the blob SHA the caller loaded the file from, or `None`
+/// for a file the caller believes is new. GitHub refuses the write when that
+/// doesn't match, which comes back as `GitHubError::Conflict` carrying the
+/// remote version so nothing gets overwritten silently.
+pub async fn upload_file_to_github(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    path_in_repo: &str,
+    commit_message: &str,
+    file_content: &str,
+    expected_sha: Option<&str>,
+) -> Result<String, GitHubError> {
+    let api_url = format!("{}/contents/{}", config.repo_api_url(), path_in_repo);
 
     let encoded_content = BASE64_STANDARD.encode(file_content.as_bytes());
 
     let request_body = GitHubPutFileRequest {
         message: commit_message,
         content: encoded_content,
-        sha: current_sha.clone(),
+        sha: expected_sha.map(str::to_string),
+        branch: &config.branch,
         committer: Committer {
             name: "Professor App",       // Using a fixed committer name
             email: "app@thebeakers.com", // Using a fixed committer email
@@ -489,76 +1342,946 @@ pub async fn upload_file_to_github(
         request_body.sha
     );
 
-    let put_response = client
-        .put(&api_url)
-        .header(AUTHORIZATION, format!("Bearer {}", token))
-        .header("Accept", "application/vnd.github+json")
-        .header("X-GitHub-Api-Version", "2022-11-28")
-        .header("User-Agent", "TauriProfessorApp/0.1.0")
-        .json(&request_body)
-        .send()
-        .await;
+    let put_response = send_github(
+        github_request(client, reqwest::Method::PUT, &api_url, token).json(&request_body),
+    )
+    .await;
 
-    match put_response {
-        Ok(resp) => {
-            let status = resp.status();
-            log::debug!("PUT response status for {}: {}", path_in_repo, status);
-            if status == StatusCode::OK || status == StatusCode::CREATED {
-                match resp.json::<GitHubPutFileResponse>().await {
-                    Ok(put_file_response) => {
-                        let action = if current_sha.is_some() {
-                            "updated"
-                        } else {
-                            "created"
-                        };
-                        let success_msg = format!(
-                            "File {} successfully {} in {}/{}. Commit SHA: {}",
-                            path_in_repo, action, owner, repo, put_file_response.commit.sha
-                        );
-                        log::info!("{}", success_msg);
-                        Ok(success_msg)
-                    }
-                    Err(e) => {
-                        log::error!(
-                            "Failed to parse successful PUT response for {}: {}",
-                            path_in_repo,
-                            e
-                        );
-                        Err(format!(
-                            "Failed to parse GitHub API response (PUT {}): {}",
-                            path_in_repo, e
-                        ))
-                    }
-                }
-            } else {
-                let error_body_text = resp
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Could not read error response body".to_string());
-                log::error!(
-                    "Raw error response body for PUT {}: {}",
-                    path_in_repo,
-                    error_body_text
-                );
-                let error_message = serde_json::from_str::<GitHubErrorResponse>(&error_body_text)
-                    .map(|gh_error| gh_error.message)
-                    .unwrap_or_else(|_| error_body_text);
-
-                log::error!(
-                    "Failed to upload file {}: {} - {}",
+    let put_file_response: GitHubPutFileResponse = match put_response {
+        Ok(resp) => parse_github_json(resp).await?,
+        Err(error) => {
+            // 409 is a SHA mismatch, 422 is "sha wasn't supplied" for a
+            // file that already exists (or some other validation error)
+            if matches!(error.status(), Some(409) | Some(422)) {
+                ensure_github_file_unchanged(client, config, token, path_in_repo, expected_sha)
+                    .await?;
+            }
+            log::error!("Failed to upload file {}: {}", path_in_repo, error);
+            return Err(error);
+        }
+    };
+
+    let action = if expected_sha.is_some() {
+        "updated"
+    } else {
+        "created"
+    };
+    let success_msg = format!(
+        "File {} successfully {} in {}/{}. Commit SHA: {}",
+        path_in_repo, action, config.owner, config.repo, put_file_response.commit.sha
+    );
+    log::info!("{}", success_msg);
+    Ok(success_msg)
+}
+
+/// One file to write in a batch commit made through the Git Data API.
+#[derive(Debug, Clone)]
+pub struct GitHubFileUpload {
+    pub path: String,
+    pub content: Vec<u8>,
+}
+
+impl GitHubFileUpload {
+    pub fn text(path: impl Into<String>, content: &str) -> Self {
+        GitHubFileUpload {
+            path: path.into(),
+            content: content.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn from_base64(path: impl Into<String>, content_base64: &str) -> Result<Self> {
+        let path = path.into();
+        let content = BASE64_STANDARD
+            .decode(content_base64)
+            .with_context(|| format!("Content for {} is not valid base64", path))?;
+        Ok(GitHubFileUpload { path, content })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubRefResponse {
+    object: GitHubShaOnly,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubShaOnly {
+    sha: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubGitCommitResponse {
+    sha: String,
+    tree: GitHubShaOnly,
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubCreateBlobRequest {
+    content: String, // Base64 encoded
+    encoding: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubTreeEntry<'a> {
+    path: &'a str,
+    mode: &'a str,
+    r#type: &'static str,
+    sha: Option<String>, // None deletes the path
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubCreateTreeRequest<'a> {
+    base_tree: &'a str,
+    tree: Vec<GitHubTreeEntry<'a>>,
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubCreateCommitRequest<'a> {
+    message: &'a str,
+    tree: &'a str,
+    parents: Vec<&'a str>,
+    committer: Committer<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubUpdateRefRequest<'a> {
+    sha: &'a str,
+    force: bool,
+}
+
+/// Writes all `files` to `branch` as a single commit using the Git Data API
+/// (blobs, trees, commits, refs). Either every file lands or none do.
+///
+/// Files that already exist keep their file mode. Paths that are a symlink,
+/// submodule or directory on the branch are refused as
+/// `GitHubError::InvalidRequest`, since writing a regular file over them
+/// would change what they are.
+///
+/// The branch ref is updated without force, so if someone pushed in the
+/// meantime GitHub rejects the update and nothing changes.
+pub async fn upload_files_to_github(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    branch: &str,
+    commit_message: &str,
+    files: &[GitHubFileUpload],
+) -> Result<String, GitHubError> {
+    let parent = github_branch_head(client, config, token, branch).await?;
+    let tree = github_blob_entries(client, config, token, &parent, files).await?;
+    let commit_sha =
+        commit_github_tree_on(client, config, token, branch, &parent, commit_message, tree).await?;
+    Ok(files_committed_message(config, branch, files, &commit_sha))
+}
+
+/// `upload_files_to_github` on top of `parent_sha`, the commit the files
+/// were read from. If `branch` has moved on since, the ref update is
+/// rejected as a `GitHubError::Conflict` and nothing changes.
+pub async fn upload_files_to_github_on(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    branch: &str,
+    parent_sha: &str,
+    commit_message: &str,
+    files: &[GitHubFileUpload],
+) -> Result<String, GitHubError> {
+    let parent = github_commit(client, config, token, parent_sha).await?;
+    let tree = github_blob_entries(client, config, token, &parent, files).await?;
+    let commit_sha =
+        commit_github_tree_on(client, config, token, branch, &parent, commit_message, tree).await?;
+    Ok(files_committed_message(config, branch, files, &commit_sha))
+}
+
+/// Uploads the content of `files` as blobs, returning the tree entries that
+/// point at them with the mode each path has in `parent`.
+async fn github_blob_entries<'a>(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    parent: &GitHubGitCommitResponse,
+    files: &'a [GitHubFileUpload],
+) -> Result<Vec<GitHubTreeEntry<'a>>, GitHubError> {
+    if files.is_empty() {
+        return Err(GitHubError::InvalidRequest {
+            message: "No files given to upload".to_string(),
+        });
+    }
+    let api_base = format!("{}/git", config.repo_api_url());
+
+    let mut tree = Vec::with_capacity(files.len());
+    for file in files {
+        let existing = github_tree_entry_at(client, config, token, &parent.sha, &file.path).await?;
+        let mode = upload_mode(&file.path, existing.as_ref())?;
+        let blob: GitHubShaOnly = send_github_json(
+            github_request(
+                client,
+                reqwest::Method::POST,
+                &format!("{}/blobs", api_base),
+                token,
+            )
+            .json(&GitHubCreateBlobRequest {
+                content: BASE64_STANDARD.encode(&file.content),
+                encoding: "base64",
+            }),
+        )
+        .await?;
+        tree.push(GitHubTreeEntry {
+            path: &file.path,
+            mode,
+            r#type: "blob",
+            sha: Some(blob.sha),
+        });
+    }
+    Ok(tree)
+}
+
+/// The mode to write `path_in_repo` with: what it already has if it is a
+/// regular or executable file, `100644` if it is new.
+fn upload_mode(
+    path_in_repo: &str,
+    existing: Option<&GitTreeEntry>,
+) -> Result<&'static str, GitHubError> {
+    match existing.map(|entry| entry.mode.as_str()) {
+        None | Some("100644") => Ok("100644"),
+        Some("100755") => Ok("100755"),
+        Some(mode) => Err(GitHubError::InvalidRequest {
+            message: format!(
+                "{} is not a regular file on the branch (mode {}), refusing to overwrite it",
+                path_in_repo, mode
+            ),
+        }),
+    }
+}
+
+fn files_committed_message(
+    config: &AppConfig,
+    branch: &str,
+    files: &[GitHubFileUpload],
+    commit_sha: &str,
+) -> String {
+    let success_msg = format!(
+        "{} file(s) committed to {}/{} ({}). Commit SHA: {}",
+        files.len(),
+        config.owner,
+        config.repo,
+        branch,
+        commit_sha
+    );
+    log::info!("{}", success_msg);
+    success_msg
+}
+
+/// The commit `branch` currently points at.
+async fn github_branch_head(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    branch: &str,
+) -> Result<GitHubGitCommitResponse, GitHubError> {
+    let head_sha = get_github_branch_head(client, config, token, branch).await?;
+    github_commit(client, config, token, &head_sha).await
+}
+
+/// SHA of the commit `branch` currently points at.
+pub async fn get_github_branch_head(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    branch: &str,
+) -> Result<String, GitHubError> {
+    let head: GitHubRefResponse = send_github_json(github_request(
+        client,
+        reqwest::Method::GET,
+        &format!("{}/git/ref/heads/{}", config.repo_api_url(), branch),
+        token,
+    ))
+    .await?;
+    Ok(head.object.sha)
+}
+
+async fn github_commit(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    commit_sha: &str,
+) -> Result<GitHubGitCommitResponse, GitHubError> {
+    send_github_json(github_request(
+        client,
+        reqwest::Method::GET,
+        &format!("{}/git/commits/{}", config.repo_api_url(), commit_sha),
+        token,
+    ))
+    .await
+}
+
+/// Commits `tree` on top of `parent` and moves `branch` to the new commit,
+/// returning its SHA. Paths not in `tree` stay as they are.
+///
+/// `parent` has to be what the caller read the branch as. The ref is updated
+/// without force, so if the branch moved since, GitHub rejects the update and
+/// nothing changes.
+async fn commit_github_tree_on(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    branch: &str,
+    parent: &GitHubGitCommitResponse,
+    commit_message: &str,
+    tree: Vec<GitHubTreeEntry<'_>>,
+) -> Result<String, GitHubError> {
+    let api_base = format!("{}/git", config.repo_api_url());
+    let ref_url = format!("{}/refs/heads/{}", api_base, branch);
+    log::info!(
+        "Committing {} path(s) on top of {} ({})",
+        tree.len(),
+        parent.sha,
+        branch
+    );
+
+    let new_tree: GitHubShaOnly = send_github_json(
+        github_request(
+            client,
+            reqwest::Method::POST,
+            &format!("{}/trees", api_base),
+            token,
+        )
+        .json(&GitHubCreateTreeRequest {
+            base_tree: &parent.tree.sha,
+            tree,
+        }),
+    )
+    .await?;
+
+    let commit: GitHubShaOnly = send_github_json(
+        github_request(
+            client,
+            reqwest::Method::POST,
+            &format!("{}/commits", api_base),
+            token,
+        )
+        .json(&GitHubCreateCommitRequest {
+            message: commit_message,
+            tree: &new_tree.sha,
+            parents: vec![&parent.sha],
+            committer: Committer {
+                name: "Professor App",
+                email: "app@thebeakers.com",
+            },
+        }),
+    )
+    .await?;
+
+    let _: serde_json::Value = send_github_json(
+        github_request(client, reqwest::Method::PATCH, &ref_url, token).json(
+            &GitHubUpdateRefRequest {
+                sha: &commit.sha,
+                force: false,
+            },
+        ),
+    )
+    .await?;
+    Ok(commit.sha)
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubDeleteFileRequest<'a> {
+    message: &'a str,
+    sha: &'a str,
+    branch: &'a str,
+    committer: Committer<'a>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubBlobResponse {
+    content: String,
+    encoding: String,
+}
+
+/// Deletes a file through the Contents API and returns the commit SHA.
+/// `expected_sha` must be the file's current blob SHA, otherwise this comes
+/// back as `GitHubError::Conflict`.
+pub async fn delete_github_file(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    path_in_repo: &str,
+    commit_message: &str,
+    expected_sha: &str,
+) -> Result<String, GitHubError> {
+    let api_url = format!("{}/contents/{}", config.repo_api_url(), path_in_repo);
+    let response = send_github(
+        github_request(client, reqwest::Method::DELETE, &api_url, token).json(
+            &GitHubDeleteFileRequest {
+                message: commit_message,
+                sha: expected_sha,
+                branch: &config.branch,
+                committer: Committer {
+                    name: "Professor App",
+                    email: "app@thebeakers.com",
+                },
+            },
+        ),
+    )
+    .await;
+    let deleted: GitHubPutFileResponse = match response {
+        Ok(resp) => parse_github_json(resp).await?,
+        Err(error) => {
+            if matches!(error.status(), Some(409) | Some(422)) {
+                ensure_github_file_unchanged(
+                    client,
+                    config,
+                    token,
                     path_in_repo,
-                    status,
-                    error_message
-                );
-                Err(format!(
-                    "GitHub API error (PUT {}): {} - {}",
-                    path_in_repo, status, error_message
-                ))
+                    Some(expected_sha),
+                )
+                .await?;
             }
+            log::error!("Failed to delete file {}: {}", path_in_repo, error);
+            return Err(error);
         }
-        Err(e) => {
-            log::error!("Network error while uploading file {}: {}", path_in_repo, e);
-            Err(format!("Network error (PUT {}): {}", path_in_repo, e))
+    };
+    log::info!(
+        "Deleted {} from {}/{}. Commit SHA: {}",
+        path_in_repo,
+        config.owner,
+        config.repo,
+        deleted.commit.sha
+    );
+    Ok(deleted.commit.sha)
+}
+
+/// The entry for `path_in_repo` in the tree of `commit_sha`, `None` if there
+/// is nothing at that path.
+async fn github_tree_entry_at(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    commit_sha: &str,
+    path_in_repo: &str,
+) -> Result<Option<GitTreeEntry>, GitHubError> {
+    let (dir, name) = path_in_repo.rsplit_once('/').unwrap_or(("", path_in_repo));
+    let tree_ish = if dir.is_empty() {
+        commit_sha.to_string()
+    } else {
+        format!("{}:{}", commit_sha, dir)
+    };
+    let api_url = format!("{}/git/trees/{}", config.repo_api_url(), tree_ish);
+    let response: GitTreeResponse = match send_github_json(github_request(
+        client,
+        reqwest::Method::GET,
+        &api_url,
+        token,
+    ))
+    .await
+    {
+        Ok(response) => response,
+        Err(GitHubError::NotFound { .. }) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(response.tree.into_iter().find(|entry| entry.path == name))
+}
+
+/// `conflict_if_changed` for a tree entry, fetching the remote content only
+/// when there is a conflict to report.
+async fn conflict_if_entry_changed(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    entry: Option<&GitTreeEntry>,
+    path_in_repo: &str,
+    expected_sha: Option<&str>,
+) -> Result<(), GitHubError> {
+    let remote = match entry {
+        Some(entry) if Some(entry.sha.as_str()) != expected_sha => Some(RemoteFile {
+            sha: entry.sha.clone(),
+            content: get_github_blob(client, config, token, &entry.sha).await?,
+        }),
+        _ => None,
+    };
+    conflict_if_changed(remote, path_in_repo, expected_sha)
+}
+
+/// Moves a file to `to_path` in a single commit on the configured branch and
+/// returns the commit SHA. The content isn't downloaded, the new path points
+/// at the same blob and keeps its file mode.
+///
+/// `expected_sha` must be the file's current blob SHA and `to_path` must not
+/// exist yet, either failing is a `GitHubError::Conflict`. Both are checked
+/// in the commit the move is then made on top of.
+pub async fn move_github_file(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    from_path: &str,
+    to_path: &str,
+    commit_message: &str,
+    expected_sha: &str,
+) -> Result<String, GitHubError> {
+    let head = github_branch_head(client, config, token, &config.branch).await?;
+    let from_entry = github_tree_entry_at(client, config, token, &head.sha, from_path).await?;
+    let Some(from_entry) = from_entry else {
+        return Err(GitHubError::NotFound {
+            path: from_path.to_string(),
+            message: format!("{} is not on {}", from_path, config.branch),
+            documentation_url: None,
+        });
+    };
+    conflict_if_entry_changed(
+        client,
+        config,
+        token,
+        Some(&from_entry),
+        from_path,
+        Some(expected_sha),
+    )
+    .await?;
+    let to_entry = github_tree_entry_at(client, config, token, &head.sha, to_path).await?;
+    conflict_if_entry_changed(client, config, token, to_entry.as_ref(), to_path, None).await?;
+
+    let tree = vec![
+        GitHubTreeEntry {
+            path: to_path,
+            mode: &from_entry.mode,
+            r#type: "blob",
+            sha: Some(expected_sha.to_string()),
+        },
+        GitHubTreeEntry {
+            path: from_path,
+            mode: &from_entry.mode,
+            r#type: "blob",
+            sha: None,
+        },
+    ];
+    let commit_sha = commit_github_tree_on(
+        client,
+        config,
+        token,
+        &config.branch,
+        &head,
+        commit_message,
+        tree,
+    )
+    .await?;
+    log::info!(
+        "Moved {} to {} in {}/{}. Commit SHA: {}",
+        from_path,
+        to_path,
+        config.owner,
+        config.repo,
+        commit_sha
+    );
+    Ok(commit_sha)
+}
+
+/// The content of a blob by SHA, also for files no longer on any branch tip.
+pub async fn get_github_blob(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    blob_sha: &str,
+) -> Result<String, GitHubError> {
+    let api_url = format!("{}/git/blobs/{}", config.repo_api_url(), blob_sha);
+    let resp = send_github(github_request(
+        client,
+        reqwest::Method::GET,
+        &api_url,
+        token,
+    ))
+    .await?;
+    let request_path = resp.url().path().to_string();
+    let blob: GitHubBlobResponse = parse_github_json(resp).await?;
+    decode_blob_content(&request_path, &blob.content, &blob.encoding)
+}
+
+/// Decodes the `content` of a blob response, which GitHub and Gitea both
+/// send as base64 wrapped at 60 columns.
+pub(crate) fn decode_blob_content(
+    request_path: &str,
+    content: &str,
+    encoding: &str,
+) -> Result<String, GitHubError> {
+    if encoding != "base64" {
+        return Ok(content.to_string());
+    }
+    let encoded: String = content.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = BASE64_STANDARD
+        .decode(encoded)
+        .map_err(|e| GitHubError::invalid_response(request_path, e))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PullRequestInfo {
+    pub number: u64,
+    pub html_url: String,
+    #[serde(default)]
+    pub created: bool, // false when an already open PR was updated
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubCreateRefRequest<'a> {
+    r#ref: String,
+    sha: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubPullRequestRequest<'a> {
+    title: &'a str,
+    body: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base: Option<&'a str>,
+}
+
+/// Branch used for reviewing an article, e.g. `article/my-article`.
+pub fn article_review_branch(file_name: &str) -> String {
+    let slug = file_name.strip_suffix(".toml").unwrap_or(file_name);
+    format!("article/{}", slug)
+}
+
+/// Markdown body for an article's review PR.
+pub fn article_pull_request_body(article: &Article) -> String {
+    let mut body = format!("## {}\n\n{}\n\n", article.title, article.description);
+    body.push_str(&format!("**Category:** {}\n\n", article.category));
+    body.push_str("### Authors\n");
+    for author in &article.authors {
+        body.push_str(&format!("- {} (`{}`)\n", author.name, author.slug));
+    }
+    body.push_str(&format!(
+        "\n### Reviewing professor\n- {} (`{}`)\n\n",
+        article.professor.name, article.professor.slug
+    ));
+    body.push_str("_Submitted via the Professor App._\n");
+    body
+}
+
+/// Creates `branch` from the tip of `base` unless it already exists.
+pub(crate) async fn ensure_github_branch(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    branch: &str,
+    base: &str,
+) -> Result<(), GitHubError> {
+    let api_base = format!("{}/git", config.repo_api_url());
+    let existing = send_github(github_request(
+        client,
+        reqwest::Method::GET,
+        &format!("{}/ref/heads/{}", api_base, branch),
+        token,
+    ))
+    .await;
+    match existing {
+        Ok(_) => {
+            log::info!("Branch {} already exists", branch);
+            return Ok(());
+        }
+        Err(GitHubError::NotFound { .. }) => {}
+        Err(e) => return Err(e),
+    }
+
+    let base_ref: GitHubRefResponse = send_github_json(github_request(
+        client,
+        reqwest::Method::GET,
+        &format!("{}/ref/heads/{}", api_base, base),
+        token,
+    ))
+    .await?;
+    let _: serde_json::Value = send_github_json(
+        github_request(
+            client,
+            reqwest::Method::POST,
+            &format!("{}/refs", api_base),
+            token,
+        )
+        .json(&GitHubCreateRefRequest {
+            r#ref: format!("refs/heads/{}", branch),
+            sha: &base_ref.object.sha,
+        }),
+    )
+    .await?;
+    log::info!("Created branch {} from {}", branch, base);
+    Ok(())
+}
+
+/// Opens a PR from `head` into `base`, or updates the title and body of the
+/// PR that is already open for `head`.
+pub async fn open_or_update_pull_request(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+    head: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+) -> Result<PullRequestInfo, GitHubError> {
+    let pulls_url = format!("{}/pulls", config.repo_api_url());
+
+    let open_pulls: Vec<PullRequestInfo> = send_github_json(
+        github_request(client, reqwest::Method::GET, &pulls_url, token).query(&[
+            ("head", format!("{}:{}", config.owner, head)),
+            ("base", base.to_string()),
+            ("state", "open".to_string()),
+        ]),
+    )
+    .await?;
+
+    match open_pulls.into_iter().next() {
+        Some(existing) => {
+            let updated: PullRequestInfo = send_github_json(
+                github_request(
+                    client,
+                    reqwest::Method::PATCH,
+                    &format!("{}/{}", pulls_url, existing.number),
+                    token,
+                )
+                .json(&GitHubPullRequestRequest {
+                    title,
+                    body,
+                    head: None,
+                    base: None,
+                }),
+            )
+            .await?;
+            log::info!("Updated pull request {}", updated.html_url);
+            Ok(updated)
+        }
+        None => {
+            let mut created: PullRequestInfo = send_github_json(
+                github_request(client, reqwest::Method::POST, &pulls_url, token).json(
+                    &GitHubPullRequestRequest {
+                        title,
+                        body,
+                        head: Some(head),
+                        base: Some(base),
+                    },
+                ),
+            )
+            .await?;
+            created.created = true;
+            log::info!("Opened pull request {}", created.html_url);
+            Ok(created)
+        }
+    }
+}
+
+/// Commits an article (and any extra files) to its own review branch and
+/// opens or updates a pull request into the configured branch for it.
+pub async fn upload_article_as_pull_request(
+    forge: &dyn Forge,
+    token: &str,
+    article: &Article,
+    file_name: &str,
+    commit_message: &str,
+    files: &[GitHubFileUpload],
+) -> Result<PullRequestInfo, GitHubError> {
+    ensure_article_valid(article)?;
+    let branch = article_review_branch(file_name);
+    let base = &forge.config().branch;
+    forge.ensure_branch(token, &branch, base).await?;
+    forge
+        .commit_files(token, &branch, commit_message, files)
+        .await?;
+    forge
+        .open_or_update_pull_request(
+            token,
+            &branch,
+            base,
+            &format!("Article: {}", article.title),
+            &article_pull_request_body(article),
+        )
+        .await
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubUser {
+    id: u64,
+    login: String,
+    name: Option<String>,
+    email: Option<String>,
+    avatar_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitHubRepoResponse {
+    full_name: String,
+    #[serde(default)]
+    permissions: RepoPermissions,
+}
+
+/// Looks up the authenticated user so local commits are attributed to them.
+/// Falls back to GitHub's noreply address when the user's email is private.
+pub async fn get_github_commit_author(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+) -> Result<CommitAuthor, GitHubError> {
+    let user: GitHubUser = send_github_json(github_request(
+        client,
+        reqwest::Method::GET,
+        &format!("{}/user", config.api_base_url),
+        token,
+    ))
+    .await?;
+    Ok(CommitAuthor {
+        name: user.name.unwrap_or_else(|| user.login.clone()),
+        email: user
+            .email
+            .unwrap_or_else(|| format!("{}+{}@users.noreply.github.com", user.id, user.login)),
+    })
+}
+
+#[derive(Serialize, Debug)]
+struct GitHubRevokeCredentialsRequest<'a> {
+    credentials: [&'a str; 1],
+}
+
+/// Revokes the token through GitHub's credential revocation API, which works
+/// with nothing but the token itself (the OAuth app endpoints need the client
+/// secret). `Ok(false)` if the server doesn't have that API, like older
+/// GitHub Enterprise versions.
+pub async fn revoke_github_token(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+) -> Result<bool, GitHubError> {
+    let revoked = send_github(
+        github_request(
+            client,
+            reqwest::Method::POST,
+            &format!("{}/credentials/revoke", config.api_base_url),
+            token,
+        )
+        .json(&GitHubRevokeCredentialsRequest {
+            credentials: [token],
+        }),
+    )
+    .await;
+    match revoked {
+        Ok(_) => {
+            log::info!("Token revoked on {}", config.api_base_url);
+            Ok(true)
+        }
+        Err(GitHubError::NotFound { .. }) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Scopes publishing needs, any one of each group will do.
+const REQUIRED_GITHUB_SCOPES: &[&[&str]] = &[&["repo", "public_repo"]];
+
+/// Calls `/user` and the configured repo to report who the token belongs to,
+/// which OAuth scopes it carries and whether it may push to the repo.
+pub async fn github_whoami(
+    client: &Client,
+    config: &AppConfig,
+    token: &str,
+) -> Result<WhoAmI, GitHubError> {
+    let user_resp = send_github(github_request(
+        client,
+        reqwest::Method::GET,
+        &format!("{}/user", config.api_base_url),
+        token,
+    ))
+    .await?;
+    // Classic OAuth and personal tokens list their scopes here, fine-grained
+    // tokens don't send the header at all
+    let scopes = user_resp
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.split(',')
+                .map(|scope| scope.trim().to_string())
+                .filter(|scope| !scope.is_empty())
+                .collect::<Vec<_>>()
+        });
+    let user: GitHubUser = parse_github_json(user_resp).await?;
+
+    let missing_scopes = match &scopes {
+        Some(scopes) => REQUIRED_GITHUB_SCOPES
+            .iter()
+            .filter(|any_of| !any_of.iter().any(|s| scopes.iter().any(|g| g == s)))
+            .map(|any_of| any_of[0].to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let repo = match send_github_json::<GitHubRepoResponse>(github_request(
+        client,
+        reqwest::Method::GET,
+        &config.repo_api_url(),
+        token,
+    ))
+    .await
+    {
+        Ok(repo) => Some(repo),
+        Err(GitHubError::NotFound { .. }) => None,
+        Err(e) => return Err(e),
+    };
+
+    let whoami = WhoAmI {
+        login: user.login,
+        name: user.name,
+        avatar_url: user.avatar_url,
+        scopes,
+        missing_scopes,
+        repo: repo
+            .as_ref()
+            .map(|repo| repo.full_name.clone())
+            .unwrap_or_else(|| format!("{}/{}", config.owner, config.repo)),
+        repo_visible: repo.is_some(),
+        permissions: repo.map(|repo| repo.permissions).unwrap_or_default(),
+    };
+    log::info!(
+        "{} on {}: push={}, scopes={:?}",
+        whoami.login,
+        whoami.repo,
+        whoami.permissions.push,
+        whoami.scopes
+    );
+    Ok(whoami)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(mode: &str, kind: &str) -> GitTreeEntry {
+        serde_json::from_value(serde_json::json!({
+            "path": "run.sh",
+            "mode": mode,
+            "type": kind,
+            "sha": "abc",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn upload_keeps_the_mode_of_regular_files() {
+        assert_eq!(upload_mode("new.toml", None).unwrap(), "100644");
+        assert_eq!(
+            upload_mode("a.toml", Some(&entry("100644", "blob"))).unwrap(),
+            "100644"
+        );
+        assert_eq!(
+            upload_mode("run.sh", Some(&entry("100755", "blob"))).unwrap(),
+            "100755"
+        );
+    }
+
+    #[test]
+    fn upload_refuses_to_replace_other_entries() {
+        for (mode, kind) in [("120000", "blob"), ("160000", "commit"), ("040000", "tree")] {
+            assert!(
+                matches!(
+                    upload_mode("run.sh", Some(&entry(mode, kind))),
+                    Err(GitHubError::InvalidRequest { .. })
+                ),
+                "{}",
+                mode
+            );
         }
     }
 }
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use substuff::upload_file_to_github; // Import the function
+use substuff::{get_github_file, upload_file_to_github, AppConfig}; // Import the functions
 
 // Configure these for your test environment
 const GITHUB_TOKEN: &str = ""; // !!! REPLACE WITH YOUR TOKEN !!!
@@ -60,13 +60,32 @@ correct_answer = "Yes, it did!"
         chrono::Utc::now()
     );
 
+    // Overwrite whatever is there, this is a test repo
+    // BEAKERS_* env vars still win, e.g. to point at a local API mock
+    let mut config = AppConfig {
+        owner: TEST_OWNER.to_string(),
+        repo: TEST_REPO.to_string(),
+        ..AppConfig::default()
+    };
+    config.apply_env_overrides();
+    let client = reqwest::Client::new();
+
+    let current_sha = match get_github_file(&client, &config, GITHUB_TOKEN, PATH_IN_REPO).await {
+        Ok(remote) => remote.map(|file| file.sha),
+        Err(error_message) => {
+            log::error!("Failed to look up existing file: {}", error_message);
+            return Ok(());
+        }
+    };
+
     match upload_file_to_github(
+        &client,
+        &config,
         GITHUB_TOKEN,
-        TEST_OWNER,
-        TEST_REPO,
         PATH_IN_REPO,
         &commit_message,
         article_toml_content,
+        current_sha.as_deref(),
     )
     .await
     {
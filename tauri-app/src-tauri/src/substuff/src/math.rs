@@ -0,0 +1,723 @@
+use crate::chem::{escape, render_ce, Cursor};
+use crate::validation::{Diagnostic, Severity};
+
+const GREEK: [(&str, &str); 40] = [
+    ("alpha", "\u{3B1}"),
+    ("beta", "\u{3B2}"),
+    ("gamma", "\u{3B3}"),
+    ("delta", "\u{3B4}"),
+    ("epsilon", "\u{3F5}"),
+    ("varepsilon", "\u{3B5}"),
+    ("zeta", "\u{3B6}"),
+    ("eta", "\u{3B7}"),
+    ("theta", "\u{3B8}"),
+    ("vartheta", "\u{3D1}"),
+    ("iota", "\u{3B9}"),
+    ("kappa", "\u{3BA}"),
+    ("lambda", "\u{3BB}"),
+    ("mu", "\u{3BC}"),
+    ("nu", "\u{3BD}"),
+    ("xi", "\u{3BE}"),
+    ("pi", "\u{3C0}"),
+    ("rho", "\u{3C1}"),
+    ("sigma", "\u{3C3}"),
+    ("tau", "\u{3C4}"),
+    ("upsilon", "\u{3C5}"),
+    ("phi", "\u{3D5}"),
+    ("varphi", "\u{3C6}"),
+    ("chi", "\u{3C7}"),
+    ("psi", "\u{3C8}"),
+    ("omega", "\u{3C9}"),
+    ("Gamma", "\u{393}"),
+    ("Delta", "\u{394}"),
+    ("Theta", "\u{398}"),
+    ("Lambda", "\u{39B}"),
+    ("Xi", "\u{39E}"),
+    ("Pi", "\u{3A0}"),
+    ("Sigma", "\u{3A3}"),
+    ("Upsilon", "\u{3A5}"),
+    ("Phi", "\u{3A6}"),
+    ("Psi", "\u{3A8}"),
+    ("Omega", "\u{3A9}"),
+    ("hbar", "\u{210F}"),
+    ("ell", "\u{2113}"),
+    ("AA", "\u{212B}"),
+];
+
+const OPERATORS: [(&str, &str); 49] = [
+    ("times", "\u{D7}"),
+    ("cdot", "\u{22C5}"),
+    ("pm", "\u{B1}"),
+    ("mp", "\u{2213}"),
+    ("div", "\u{F7}"),
+    ("ast", "\u{2217}"),
+    ("to", "\u{2192}"),
+    ("rightarrow", "\u{2192}"),
+    ("longrightarrow", "\u{27F6}"),
+    ("leftarrow", "\u{2190}"),
+    ("longleftarrow", "\u{27F5}"),
+    ("leftrightarrow", "\u{2194}"),
+    ("Rightarrow", "\u{21D2}"),
+    ("Leftarrow", "\u{21D0}"),
+    ("Leftrightarrow", "\u{21D4}"),
+    ("rightleftharpoons", "\u{21CC}"),
+    ("uparrow", "\u{2191}"),
+    ("downarrow", "\u{2193}"),
+    ("approx", "\u{2248}"),
+    ("sim", "\u{223C}"),
+    ("simeq", "\u{2243}"),
+    ("propto", "\u{221D}"),
+    ("leq", "\u{2264}"),
+    ("le", "\u{2264}"),
+    ("geq", "\u{2265}"),
+    ("ge", "\u{2265}"),
+    ("ll", "\u{226A}"),
+    ("gg", "\u{226B}"),
+    ("neq", "\u{2260}"),
+    ("ne", "\u{2260}"),
+    ("equiv", "\u{2261}"),
+    ("infty", "\u{221E}"),
+    ("partial", "\u{2202}"),
+    ("nabla", "\u{2207}"),
+    ("degree", "\u{B0}"),
+    ("circ", "\u{2218}"),
+    ("cdots", "\u{22EF}"),
+    ("ldots", "\u{2026}"),
+    ("dots", "\u{2026}"),
+    ("sum", "\u{2211}"),
+    ("prod", "\u{220F}"),
+    ("int", "\u{222B}"),
+    ("oint", "\u{222E}"),
+    ("in", "\u{2208}"),
+    ("langle", "\u{27E8}"),
+    ("rangle", "\u{27E9}"),
+    ("prime", "\u{2032}"),
+    ("perp", "\u{22A5}"),
+    ("parallel", "\u{2225}"),
+];
+
+/// Written upright, e.g. `\ln x`.
+const FUNCTIONS: [&str; 14] = [
+    "sin", "cos", "tan", "log", "ln", "exp", "lim", "max", "min", "det", "sinh", "cosh", "tanh",
+    "arg",
+];
+
+const ACCENTS: [(&str, &str); 6] = [
+    ("bar", "\u{AF}"),
+    ("overline", "\u{AF}"),
+    ("vec", "\u{2192}"),
+    ("hat", "^"),
+    ("dot", "\u{2D9}"),
+    ("tilde", "~"),
+];
+
+const SPACES: [(&str, &str); 7] = [
+    (",", "0.1667em"),
+    (":", "0.2222em"),
+    (">", "0.2222em"),
+    (";", "0.2778em"),
+    (" ", "0.25em"),
+    ("quad", "1em"),
+    ("qquad", "2em"),
+];
+
+fn lookup<'a>(table: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    table
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| *value)
+}
+
+/// MathML for one formula, and notes like unbalanced reactions.
+pub(crate) struct Rendered {
+    pub mathml: String,
+    pub warnings: Vec<String>,
+}
+
+struct TexParser {
+    cursor: Cursor,
+    warnings: Vec<String>,
+}
+
+impl TexParser {
+    /// Atoms up to the end, or up to and including `}` inside a group.
+    fn parse_row(&mut self, in_group: bool) -> Result<String, String> {
+        let mut row = String::new();
+        loop {
+            self.cursor.take_while(char::is_whitespace);
+            match self.cursor.peek() {
+                None if in_group => return Err("missing \"}\"".to_string()),
+                None => return Ok(row),
+                Some('}') if in_group => {
+                    self.cursor.pos += 1;
+                    return Ok(row);
+                }
+                Some('}') => return Err("unexpected \"}\"".to_string()),
+                Some(_) => row.push_str(&self.parse_scripted()?),
+            }
+        }
+    }
+
+    /// An atom with any `^` and `_` after it.
+    fn parse_scripted(&mut self) -> Result<String, String> {
+        let base = match self.cursor.peek() {
+            Some('^' | '_') => "<mrow></mrow>".to_string(),
+            _ => self.parse_atom()?,
+        };
+        let (mut sub, mut sup) = (None, None);
+        while let Some(c @ ('^' | '_')) = self.cursor.peek() {
+            self.cursor.pos += 1;
+            let slot = if c == '^' { &mut sup } else { &mut sub };
+            if slot.is_some() {
+                return Err(format!("double \"{}\"", c));
+            }
+            *slot = Some(self.parse_argument()?);
+        }
+        Ok(match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<msub>{}{}</msub>", base, sub),
+            (None, Some(sup)) => format!("<msup>{}{}</msup>", base, sup),
+            (Some(sub), Some(sup)) => format!("<msubsup>{}{}{}</msubsup>", base, sub, sup),
+        })
+    }
+
+    /// `{...}` or a single atom, what `\frac` and `^` take.
+    fn parse_argument(&mut self) -> Result<String, String> {
+        self.cursor.take_while(char::is_whitespace);
+        match self.cursor.peek() {
+            None | Some('}') => Err("missing argument".to_string()),
+            // `x^23` is x² followed by 3, like TeX
+            Some(c) if c.is_ascii_digit() => {
+                self.cursor.pos += 1;
+                Ok(format!("<mn>{}</mn>", c))
+            }
+            Some(_) => self.parse_atom(),
+        }
+    }
+
+    /// `{...}` taken verbatim, for `\text` and `\ce`.
+    fn parse_raw_argument(&mut self, command: &str) -> Result<String, String> {
+        self.cursor.take_while(char::is_whitespace);
+        if self.cursor.peek() != Some('{') {
+            return Err(format!("\\{} needs {{...}}", command));
+        }
+        self.cursor.pos += 1;
+        self.cursor.take_balanced('{', '}')
+    }
+
+    fn parse_atom(&mut self) -> Result<String, String> {
+        let c = self.cursor.peek().ok_or("missing argument")?;
+        self.cursor.pos += 1;
+        Ok(match c {
+            '{' => format!("<mrow>{}</mrow>", self.parse_row(true)?),
+            '\\' => self.parse_command()?,
+            '0'..='9' | '.'
+                if c != '.' || self.cursor.peek().is_some_and(|c| c.is_ascii_digit()) =>
+            {
+                self.cursor.pos -= 1;
+                let number = self.cursor.take_while(|c| c.is_ascii_digit() || c == '.');
+                format!("<mn>{}</mn>", number)
+            }
+            c if c.is_alphabetic() => format!("<mi>{}</mi>", c),
+            '-' => "<mo>\u{2212}</mo>".to_string(),
+            '*' => "<mo>\u{2217}</mo>".to_string(),
+            '\'' => "<mo>\u{2032}</mo>".to_string(),
+            '~' => "<mspace width=\"0.25em\"></mspace>".to_string(),
+            '&' | '#' | '%' => return Err(format!("\"{}\" isn't supported, write \\{}", c, c)),
+            c => format!("<mo>{}</mo>", escape(&c.to_string())),
+        })
+    }
+
+    fn parse_command(&mut self) -> Result<String, String> {
+        let mut name = self.cursor.take_while(|c| c.is_ascii_alphabetic());
+        if name.is_empty() {
+            let c = self.cursor.peek().ok_or("\"\\\" at the end")?;
+            self.cursor.pos += 1;
+            name.push(c);
+        }
+        let name = name.as_str();
+
+        if let Some(symbol) = lookup(&GREEK, name) {
+            let upright = symbol.chars().all(|c| c.is_uppercase());
+            return Ok(if upright {
+                format!("<mi mathvariant=\"normal\">{}</mi>", symbol)
+            } else {
+                format!("<mi>{}</mi>", symbol)
+            });
+        }
+        if let Some(symbol) = lookup(&OPERATORS, name) {
+            return Ok(format!("<mo>{}</mo>", symbol));
+        }
+        if FUNCTIONS.contains(&name) {
+            return Ok(format!("<mi>{}</mi>", name));
+        }
+        if let Some(width) = lookup(&SPACES, name) {
+            return Ok(format!("<mspace width=\"{}\"></mspace>", width));
+        }
+        if let Some(accent) = lookup(&ACCENTS, name) {
+            let base = self.parse_argument()?;
+            return Ok(format!(
+                "<mover accent=\"true\">{}<mo>{}</mo></mover>",
+                base, accent
+            ));
+        }
+        Ok(match name {
+            "{" | "}" | "$" | "%" | "&" | "#" | "_" | "|" => format!("<mo>{}</mo>", escape(name)),
+            "!" => "<mspace width=\"-0.1667em\"></mspace>".to_string(),
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.parse_argument()?;
+                let denominator = self.parse_argument()?;
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "sqrt" => {
+                self.cursor.take_while(char::is_whitespace);
+                if self.cursor.peek() == Some('[') {
+                    self.cursor.pos += 1;
+                    let index = self.cursor.take_balanced('[', ']')?;
+                    let index = render_tex_row(&index, &mut self.warnings)?;
+                    format!(
+                        "<mroot>{}<mrow>{}</mrow></mroot>",
+                        self.parse_argument()?,
+                        index
+                    )
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.parse_argument()?)
+                }
+            }
+            "text" | "textrm" | "mbox" => {
+                format!("<mtext>{}</mtext>", escape(&self.parse_raw_argument(name)?))
+            }
+            "mathrm" | "operatorname" => format!(
+                "<mi mathvariant=\"normal\">{}</mi>",
+                escape(&self.parse_raw_argument(name)?)
+            ),
+            "mathbf" => format!(
+                "<mi mathvariant=\"bold\">{}</mi>",
+                escape(&self.parse_raw_argument(name)?)
+            ),
+            "left" | "right" => {
+                self.cursor.take_while(char::is_whitespace);
+                match self.cursor.peek() {
+                    Some('.') => {
+                        self.cursor.pos += 1;
+                        String::new()
+                    }
+                    Some(_) => self.parse_atom()?,
+                    None => return Err(format!("\\{} needs a delimiter", name)),
+                }
+            }
+            "ce" => {
+                let chem = render_ce(&self.parse_raw_argument(name)?)?;
+                self.warnings.extend(chem.unbalanced);
+                chem.mathml
+            }
+            name => return Err(format!("unknown command \\{}", name)),
+        })
+    }
+}
+
+fn render_tex_row(source: &str, warnings: &mut Vec<String>) -> Result<String, String> {
+    let mut parser = TexParser {
+        cursor: Cursor::new(source),
+        warnings: Vec::new(),
+    };
+    let row = parser.parse_row(false)?;
+    warnings.extend(parser.warnings);
+    Ok(row)
+}
+
+fn math_element(row: &str, display: bool) -> String {
+    if display {
+        format!("<math display=\"block\"><mrow>{}</mrow></math>", row)
+    } else {
+        format!("<math><mrow>{}</mrow></math>", row)
+    }
+}
+
+/// Renders the LaTeX math subset articles use (scripts, fractions, roots,
+/// Greek letters, common operators and arrows, `\text` and `\ce`) to MathML.
+pub(crate) fn render_tex(source: &str, display: bool) -> Result<Rendered, String> {
+    let mut warnings = Vec::new();
+    let row = render_tex_row(source, &mut warnings)?;
+    Ok(Rendered {
+        mathml: math_element(&row, display),
+        warnings,
+    })
+}
+
+fn render_chem(source: &str) -> Result<Rendered, String> {
+    let chem = render_ce(source)?;
+    Ok(Rendered {
+        mathml: math_element(&chem.mathml, false),
+        warnings: chem.unbalanced.into_iter().collect(),
+    })
+}
+
+/// A piece of body text, either plain or a formula to render.
+enum Span<'a> {
+    Text(&'a str),
+    Math {
+        raw: &'a str,
+        source: &'a str,
+        display: bool,
+    },
+    Chem {
+        raw: &'a str,
+        source: &'a str,
+    },
+    /// `\ce{` without its `}`
+    Unclosed(&'a str),
+}
+
+/// Byte index of the `}` closing the `{` just before `from`.
+fn closing_brace(text: &str, from: usize) -> Option<usize> {
+    let mut depth = 1;
+    for (i, c) in text[from..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(from + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Byte index of the `$` closing inline math opened at `open`. Like pandoc,
+/// the content can't start or end with a space and the closing `$` can't be
+/// followed by a digit, so "costs $5 or $10" stays text.
+fn closing_dollar(text: &str, open: usize) -> Option<usize> {
+    let content = &text[open + 1..];
+    if content.starts_with(char::is_whitespace) || content.starts_with('$') {
+        return None;
+    }
+    let mut previous = None;
+    for (i, c) in content.char_indices() {
+        if c == '$'
+            && previous.is_some_and(|p: char| !p.is_whitespace() && p != '\\')
+            && !content[i + 1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            return Some(open + 1 + i);
+        }
+        previous = Some(c);
+    }
+    None
+}
+
+/// Splits `text` into plain text, `\ce{...}` and, if `dollars` is set,
+/// `$...$` and `$$...$$` math. `\$` is a literal dollar sign.
+fn split_spans(text: &str, dollars: bool) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let span = if dollars && rest.starts_with("\\$") {
+            spans.push(Span::Text(&text[start..i]));
+            start = i + 1;
+            i += 2;
+            continue;
+        } else if rest.starts_with("\\ce{") {
+            match closing_brace(text, i + 4) {
+                Some(end) => Some((
+                    Span::Chem {
+                        raw: &text[i..=end],
+                        source: &text[i + 4..end],
+                    },
+                    end + 1,
+                )),
+                None => Some((Span::Unclosed(rest), text.len())),
+            }
+        } else if dollars && rest.starts_with("$$") {
+            rest[2..].find("$$").map(|end| {
+                let end = i + 2 + end;
+                (
+                    Span::Math {
+                        raw: &text[i..end + 2],
+                        source: &text[i + 2..end],
+                        display: true,
+                    },
+                    end + 2,
+                )
+            })
+        } else if dollars && rest.starts_with('$') {
+            closing_dollar(text, i).map(|end| {
+                (
+                    Span::Math {
+                        raw: &text[i..=end],
+                        source: &text[i + 1..end],
+                        display: false,
+                    },
+                    end + 1,
+                )
+            })
+        } else {
+            None
+        };
+        match span {
+            Some((span, next)) => {
+                spans.push(Span::Text(&text[start..i]));
+                spans.push(span);
+                i = next;
+                start = next;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    spans.push(Span::Text(&text[start..]));
+    spans.retain(|span| !matches!(span, Span::Text("")));
+    spans
+}
+
+fn report(issues: &mut Vec<Diagnostic>, severity: Severity, raw: &str, message: &str) {
+    let mut shown: String = raw.chars().take(40).collect();
+    if shown.len() < raw.len() {
+        shown.push('\u{2026}');
+    }
+    issues.push(Diagnostic::new(
+        severity,
+        "body",
+        format!("{}: {}", shown, message),
+    ));
+}
+
+/// Renders one formula, noting problems in `issues`. A formula that doesn't
+/// parse is left as its source text.
+fn render_formula(
+    raw: &str,
+    rendered: Result<Rendered, String>,
+    issues: &mut Vec<Diagnostic>,
+) -> Option<String> {
+    match rendered {
+        Ok(rendered) => {
+            for warning in &rendered.warnings {
+                report(
+                    issues,
+                    Severity::Warning,
+                    raw,
+                    &format!("not balanced, {}", warning),
+                );
+            }
+            Some(rendered.mathml)
+        }
+        Err(message) => {
+            report(issues, Severity::Error, raw, &message);
+            None
+        }
+    }
+}
+
+/// Part of a Markdown text event after `render_chem_in_text`.
+pub(crate) enum TextPiece<'a> {
+    Text(&'a str),
+    Html(String),
+}
+
+/// Splits Markdown text at `\ce{}` formulas, which come back as MathML.
+pub(crate) fn render_chem_in_text<'a>(
+    text: &'a str,
+    issues: &mut Vec<Diagnostic>,
+) -> Vec<TextPiece<'a>> {
+    split_spans(text, false)
+        .into_iter()
+        .map(|span| match span {
+            Span::Text(text) | Span::Math { raw: text, .. } => TextPiece::Text(text),
+            Span::Chem { raw, source } => match render_formula(raw, render_chem(source), issues) {
+                Some(mathml) => TextPiece::Html(mathml),
+                None => TextPiece::Text(raw),
+            },
+            Span::Unclosed(raw) => {
+                report(issues, Severity::Error, raw, "missing \"}\"");
+                TextPiece::Text(raw)
+            }
+        })
+        .collect()
+}
+
+/// `$...$` or `$$...$$` from the Markdown parser as MathML, or `None` with
+/// the problem noted in `issues`.
+pub(crate) fn render_markdown_math(
+    source: &str,
+    display: bool,
+    issues: &mut Vec<Diagnostic>,
+) -> Option<String> {
+    let raw = if display {
+        format!("$${}$$", source)
+    } else {
+        format!("${}$", source)
+    };
+    render_formula(&raw, render_tex(source, display), issues)
+}
+
+/// The few entities a formula in an HTML body is likely to contain.
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn render_html_text(text: &str, out: &mut String, issues: &mut Vec<Diagnostic>) {
+    for span in split_spans(text, true) {
+        let (raw, rendered) = match span {
+            Span::Text(text) => {
+                out.push_str(text);
+                continue;
+            }
+            Span::Math {
+                raw,
+                source,
+                display,
+            } => (raw, render_tex(&decode_entities(source), display)),
+            Span::Chem { raw, source } => (raw, render_chem(&decode_entities(source))),
+            Span::Unclosed(raw) => (raw, Err("missing \"}\"".to_string())),
+        };
+        match render_formula(raw, rendered, issues) {
+            Some(mathml) => out.push_str(&mathml),
+            None => out.push_str(raw),
+        }
+    }
+}
+
+/// Elements whose content is never treated as formulas.
+const VERBATIM_TAGS: [&str; 6] = ["code", "pre", "script", "style", "textarea", "math"];
+
+/// Replaces `$...$`, `$$...$$` and `\ce{...}` in the text of an HTML body with
+/// MathML, leaving tags, comments and code alone.
+pub(crate) fn render_html_math(html: &str, issues: &mut Vec<Diagnostic>) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(lt) = rest.find('<') {
+        render_html_text(&rest[..lt], &mut out, issues);
+        rest = &rest[lt..];
+        let tag_end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|end| end + 3)
+        } else {
+            rest.find('>').map(|end| end + 1)
+        }
+        .unwrap_or(rest.len());
+        let tag = &rest[..tag_end];
+        let name: String = tag[1..]
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_lowercase();
+        // Copy verbatim elements through to their closing tag
+        let verbatim_end = if VERBATIM_TAGS.contains(&name.as_str()) {
+            let closing = format!("</{}", name);
+            rest.to_ascii_lowercase()[tag_end..]
+                .find(&closing)
+                .map(|at| {
+                    let after = tag_end + at;
+                    rest[after..]
+                        .find('>')
+                        .map_or(rest.len(), |end| after + end + 1)
+                })
+                .unwrap_or(rest.len())
+        } else {
+            tag_end
+        };
+        out.push_str(&rest[..verbatim_end]);
+        rest = &rest[verbatim_end..];
+    }
+    render_html_text(rest, &mut out, issues);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn html(body: &str) -> (String, Vec<Diagnostic>) {
+        let mut issues = Vec::new();
+        let out = render_html_math(body, &mut issues);
+        (out, issues)
+    }
+
+    #[test]
+    fn less_than_is_escaped_in_mathml() {
+        let expected = concat!(
+            "<math><mrow><msup><mi>x</mi><mn>2</mn></msup>",
+            "<mo>&lt;</mo><mi>y</mi></mrow></math>"
+        );
+        let mut issues = Vec::new();
+        assert_eq!(
+            render_markdown_math("x^2 < y", false, &mut issues).as_deref(),
+            Some(expected)
+        );
+        assert_eq!(
+            html("<p>$x^2 &lt; y$</p>"),
+            (format!("<p>{}</p>", expected), vec![])
+        );
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn ce_in_html_renders_and_notes_an_unbalanced_reaction() {
+        let (out, issues) = html("<p>\\ce{H2 + O2 -> H2O}</p>");
+        assert_eq!(
+            out,
+            concat!(
+                "<p><math><mrow><mrow>",
+                "<mrow><msub><mi mathvariant=\"normal\">H</mi><mn>2</mn></msub></mrow>",
+                "<mo>+</mo>",
+                "<mrow><msub><mi mathvariant=\"normal\">O</mi><mn>2</mn></msub></mrow>",
+                "<mo stretchy=\"true\">\u{2192}</mo>",
+                "<mrow><msub><mi mathvariant=\"normal\">H</mi><mn>2</mn></msub><mi mathvariant=\"normal\">O</mi></mrow>",
+                "</mrow></mrow></math></p>"
+            )
+        );
+        assert_eq!(
+            issues,
+            vec![Diagnostic::new(
+                Severity::Warning,
+                "body",
+                "\\ce{H2 + O2 -> H2O}: not balanced, O 2 \u{2192} 1"
+            )]
+        );
+    }
+
+    #[test]
+    fn unterminated_ce_stays_source_with_an_error() {
+        let (out, issues) = html("<p>Water \\ce{H2O and more</p>");
+        assert_eq!(out, "<p>Water \\ce{H2O and more</p>");
+        assert_eq!(
+            issues,
+            vec![Diagnostic::new(
+                Severity::Error,
+                "body",
+                "\\ce{H2O and more: missing \"}\""
+            )]
+        );
+
+        let mut issues = Vec::new();
+        let pieces = render_chem_in_text("Water \\ce{H2O", &mut issues);
+        assert!(matches!(
+            pieces.as_slice(),
+            [TextPiece::Text("Water "), TextPiece::Text("\\ce{H2O")]
+        ));
+        assert_eq!(issues.len(), 1);
+    }
+
+    #[test]
+    fn unterminated_dollar_stays_text() {
+        assert_eq!(
+            html("<p>costs $5 only</p>"),
+            ("<p>costs $5 only</p>".to_string(), vec![])
+        );
+        assert_eq!(
+            html("<p>costs $5 or $10</p>"),
+            ("<p>costs $5 or $10</p>".to_string(), vec![])
+        );
+        assert_eq!(
+            html("<p>$x^2 and more</p>"),
+            ("<p>$x^2 and more</p>".to_string(), vec![])
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::{bail, Context, Result};
+use toml::{Table, Value};
+
+/// The `schemaVersion` new and migrated articles are written with.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// Upgrades a document from `to - 1` to `to`.
+struct Migration {
+    to: u32,
+    description: &'static str,
+    apply: fn(&mut Table),
+}
+
+/// Every change to the file format, oldest first. Files from before
+/// `schemaVersion` existed are version 0. A new step goes at the end and
+/// bumps `CURRENT_SCHEMA_VERSION`, old steps never change.
+const MIGRATIONS: [Migration; CURRENT_SCHEMA_VERSION as usize] = [
+    Migration {
+        to: 1,
+        description: "every article has a category",
+        apply: add_category,
+    },
+    Migration {
+        to: 2,
+        description: "unset dates are left out",
+        apply: drop_unset_dates,
+    },
+];
+
+pub(crate) fn current_schema_version() -> u32 {
+    CURRENT_SCHEMA_VERSION
+}
+
+/// The version an article document says it is in, 0 for documents from
+/// before versioning.
+pub fn schema_version(document: &Table) -> Result<u32> {
+    match document.get("schemaVersion") {
+        None => Ok(0),
+        Some(Value::Integer(version)) => u32::try_from(*version)
+            .with_context(|| format!("schemaVersion {} is not a version", version)),
+        Some(other) => bail!("schemaVersion must be a number, not {}", other),
+    }
+}
+
+/// Runs every migration `document` hasn't had yet, in order, and fills in an
+/// unset `createdAt`. Returns the version it started at.
+pub fn migrate(document: &mut Table) -> Result<u32> {
+    let version = schema_version(document)?;
+    if version > CURRENT_SCHEMA_VERSION {
+        bail!(
+            "The article is schemaVersion {}, this editor only knows up to {}, update the app",
+            version,
+            CURRENT_SCHEMA_VERSION
+        );
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        log::debug!(
+            "Migrating article to schemaVersion {}: {}",
+            migration.to,
+            migration.description
+        );
+        (migration.apply)(document);
+    }
+    crate::timestamps::default_created_at(document);
+    document.insert(
+        "schemaVersion".to_string(),
+        Value::Integer(CURRENT_SCHEMA_VERSION.into()),
+    );
+    Ok(version)
+}
+
+/// Articles from the Python exporter have none, validation asks for one.
+fn add_category(document: &mut Table) {
+    document
+        .entry("category")
+        .or_insert_with(|| Value::String(String::new()));
+}
+
+fn drop_unset_dates(document: &mut Table) {
+    for key in ["updatedAt", "lastUpdatedAt"] {
+        if let Some(Value::String(value)) = document.get(key) {
+            if matches!(value.trim(), "" | "None" | "null") {
+                document.remove(key);
+            }
+        }
+    }
+}
+
+/// The current-version TOML for an article file, `None` if it already is
+/// current. Migrated files are rewritten in the editor's own layout.
+pub fn upgrade_article_toml(toml_content: &str) -> Result<Option<String>> {
+    let mut document: Table = toml::from_str(toml_content).context("The file is not valid TOML")?;
+    if migrate(&mut document)? == CURRENT_SCHEMA_VERSION {
+        return Ok(None);
+    }
+    let article: crate::Article = document
+        .try_into()
+        .context("Failed to parse the migrated TOML into an Article struct")?;
+    Ok(Some(toml::to_string_pretty(&article)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An article as the Python exporter wrote them, before `schemaVersion`.
+    const EXPORTED: &str = include_str!("../testing.toml");
+
+    fn document(toml: &str) -> Table {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn exported_article_migrates_to_current() {
+        let mut exported = document(EXPORTED);
+        assert_eq!(schema_version(&exported).unwrap(), 0);
+        assert_eq!(migrate(&mut exported).unwrap(), 0);
+
+        let expected = {
+            let mut table = document(EXPORTED);
+            table.remove("updatedAt");
+            table.remove("lastUpdatedAt");
+            table.insert("category".to_string(), Value::String(String::new()));
+            table.insert("schemaVersion".to_string(), Value::Integer(2));
+            table
+        };
+        assert_eq!(exported, expected);
+        assert_eq!(exported["readingTime"].as_integer(), Some(10));
+
+        let upgraded = upgrade_article_toml(EXPORTED).unwrap().unwrap();
+        let article = crate::article_from_toml_str(&upgraded).unwrap();
+        assert_eq!(article.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(article.category, "");
+        assert_eq!(article.updated_at, None);
+        assert_eq!(article.questions.unwrap()[0].answers.len(), 4);
+    }
+
+    #[test]
+    fn migrating_twice_changes_nothing() {
+        let mut once = document(EXPORTED);
+        migrate(&mut once).unwrap();
+        let mut twice = once.clone();
+        assert_eq!(migrate(&mut twice).unwrap(), CURRENT_SCHEMA_VERSION);
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn later_steps_keep_what_is_already_there() {
+        let mut partly = document(&format!(
+            "schemaVersion = 1\ncategory = \"chemistry\"\n{}",
+            EXPORTED
+        ));
+        assert_eq!(migrate(&mut partly).unwrap(), 1);
+        assert_eq!(partly["category"].as_str(), Some("chemistry"));
+        assert!(!partly.contains_key("updatedAt"));
+    }
+
+    #[test]
+    fn current_files_are_not_rewritten() {
+        let upgraded = upgrade_article_toml(EXPORTED).unwrap().unwrap();
+        assert!(upgraded.contains("schemaVersion = 2"));
+        assert_eq!(upgrade_article_toml(&upgraded).unwrap(), None);
+    }
+
+    #[test]
+    fn newer_and_malformed_versions_are_refused() {
+        let mut newer = document(&format!("schemaVersion = {}", CURRENT_SCHEMA_VERSION + 1));
+        let error = migrate(&mut newer).unwrap_err().to_string();
+        assert!(error.contains("update the app"), "{}", error);
+        assert!(upgrade_article_toml(&format!("schemaVersion = 3\n{}", EXPORTED)).is_err());
+
+        assert!(schema_version(&document("schemaVersion = \"2\"")).is_err());
+        assert!(schema_version(&document("schemaVersion = -1")).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::body::{render_body, BodyFormat};
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Reading speed for science writing, slower than the usual 250 for prose.
+pub const WORDS_PER_MINUTE: u32 = 200;
+/// Time spent on a formula or reaction, however long its source is.
+const SECONDS_PER_FORMULA: u32 = 5;
+/// Time spent looking at an image.
+const SECONDS_PER_FIGURE: u32 = 12;
+
+/// Tags that don't separate words, so `H<sub>2</sub>O` is one word.
+const INLINE_TAGS: [&str; 14] = [
+    "a", "abbr", "b", "code", "em", "i", "mark", "s", "small", "span", "strong", "sub", "sup", "u",
+];
+
+/// What the reading time of a body is made of.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, TS)]
+pub struct ReadingStats {
+    pub words: u32,
+    pub formulas: u32,
+    pub figures: u32,
+    /// Rounded up, 0 only for an empty body.
+    pub minutes: u32,
+}
+
+/// Counts the words, formulas and images in the HTML `body` publishes as and
+/// estimates how long it takes to read.
+pub fn reading_stats(body: &str, format: BodyFormat) -> ReadingStats {
+    let html = render_body(body, format);
+    let mut stats = ReadingStats::default();
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+
+    while let Some(lt) = rest.find(['<', '&']) {
+        text.push_str(&rest[..lt]);
+        rest = &rest[lt..];
+        if rest.starts_with('&') {
+            // Entities count as a letter, except the non-breaking space
+            let end = rest.find(';').map_or(1, |semicolon| semicolon + 1);
+            text.push(if rest.starts_with("&nbsp;") { ' ' } else { '&' });
+            rest = &rest[end..];
+            continue;
+        }
+
+        let end = rest.find('>').map_or(rest.len(), |gt| gt + 1);
+        let tag = &rest[..end];
+        let name: String = tag
+            .trim_start_matches(['<', '/'])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        rest = &rest[end..];
+
+        match name.as_str() {
+            "math" if !tag.starts_with("</") => {
+                stats.formulas += 1;
+                rest = rest.find("</math>").map_or("", |close| &rest[close + 7..]);
+                text.push(' ');
+            }
+            "img" => {
+                stats.figures += 1;
+                text.push(' ');
+            }
+            name if INLINE_TAGS.contains(&name) => {}
+            _ => text.push(' '),
+        }
+    }
+    text.push_str(rest);
+
+    stats.words = text
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count() as u32;
+
+    let seconds = stats.words * 60 / WORDS_PER_MINUTE
+        + stats.formulas * SECONDS_PER_FORMULA
+        + stats.figures * SECONDS_PER_FIGURE;
+    stats.minutes = match seconds.div_ceil(60) {
+        0 if stats.words + stats.formulas + stats.figures > 0 => 1,
+        minutes => minutes,
+    };
+    stats
+}
+
+/// Whether a hand-entered reading time is far enough from the computed one
+/// to point out: off by more than half, and by at least 2 minutes.
+pub fn reading_time_diverges(stored: u32, computed: u32) -> bool {
+    let difference = stored.abs_diff(computed);
+    difference >= 2 && difference * 2 > computed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn html(body: &str) -> ReadingStats {
+        reading_stats(body, BodyFormat::Html)
+    }
+
+    #[test]
+    fn inline_tags_join_words_and_block_tags_split_them() {
+        assert_eq!(html("<p>H<sub>2</sub>O is <em>wet</em>ter</p>").words, 3);
+        assert_eq!(html("<p>one</p><p>two</p>").words, 2);
+        assert_eq!(html("one<br>two").words, 2);
+    }
+
+    #[test]
+    fn formulas_count_as_formulas_not_words() {
+        let stats = html("<p>Water is \\ce{H2O}, and $x^2 + y^2$ too.</p>");
+        assert_eq!((stats.words, stats.formulas), (4, 2));
+        let stats = html("<p>See <math><mi>x</mi></math><img src=\"a.png\" alt=\"a\"></p>");
+        assert_eq!((stats.words, stats.formulas, stats.figures), (1, 1, 1));
+    }
+
+    #[test]
+    fn entities_are_letters_and_nbsp_is_a_space() {
+        assert_eq!(html("caf&eacute; au&nbsp;lait").words, 3);
+        assert_eq!(html("salt &amp; pepper").words, 2);
+    }
+
+    #[test]
+    fn minutes_round_up_and_are_only_0_when_empty() {
+        assert_eq!(html("").minutes, 0);
+        assert_eq!(html("<p> </p>").minutes, 0);
+        assert_eq!(html("word").minutes, 1);
+        let words = |n: usize| vec!["word"; n].join(" ");
+        assert_eq!(html(&words(200)).minutes, 1);
+        assert_eq!(html(&words(201)).minutes, 1);
+        assert_eq!(html(&words(204)).minutes, 2);
+        // 12 formulas at 5 s each is a minute of its own
+        let formulas = "$x$ ".repeat(12);
+        assert_eq!(html(&format!("{} {}", words(200), formulas)).minutes, 2);
+    }
+
+    #[test]
+    fn reading_time_diverges_at_two_minutes_and_half() {
+        assert!(!reading_time_diverges(0, 0));
+        assert!(!reading_time_diverges(2, 1));
+        assert!(reading_time_diverges(3, 1));
+        assert!(!reading_time_diverges(6, 4));
+        assert!(reading_time_diverges(7, 4));
+        assert!(reading_time_diverges(1, 4));
+        assert!(!reading_time_diverges(2, 4));
+        assert!(!reading_time_diverges(15, 10));
+        assert!(reading_time_diverges(16, 10));
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{
+    Article, ArticleChange, ArticleSummary, Author, BodyFormat, Diagnostic, Image, LoadedArticle,
+    MigrationSummary, Professor, Question, ReadingStats, Severity,
+};
+use schemars::schema_for;
+use ts_rs::TS;
+
+/// Where `generated_files` go, relative to the repo root.
+pub const ARTICLE_SCHEMA_PATH: &str = "Types/article.schema.json";
+pub const TYPESCRIPT_PATH: &str = "Types/generated.ts";
+
+const TYPESCRIPT_HEADER: &str = "\
+// Generated from the Rust types in substuff, don't edit.
+// Run `cargo run --bin generate_types` in tauri-app/src-tauri/src/substuff to update.
+";
+
+/// JSON Schema of an article TOML file, for tools that check the website
+/// repo.
+pub fn article_json_schema() -> String {
+    let schema = schema_for!(Article);
+    serde_json::to_string_pretty(&schema).expect("a schema is always valid JSON") + "\n"
+}
+
+fn declaration<T: TS>() -> String {
+    let docs = T::docs().unwrap_or_default();
+    format!("{}export {}\n", docs, T::decl())
+}
+
+/// TypeScript definitions of the article and of what the app's commands
+/// send to the frontend.
+pub fn typescript_definitions() -> String {
+    [
+        TYPESCRIPT_HEADER.to_string(),
+        declaration::<Article>(),
+        declaration::<BodyFormat>(),
+        declaration::<Image>(),
+        declaration::<Author>(),
+        declaration::<Professor>(),
+        declaration::<Question>(),
+        declaration::<ArticleSummary>(),
+        declaration::<LoadedArticle>(),
+        declaration::<ArticleChange>(),
+        declaration::<Diagnostic>(),
+        declaration::<Severity>(),
+        declaration::<ReadingStats>(),
+        declaration::<MigrationSummary>(),
+    ]
+    .join("\n")
+}
+
+/// Every generated file with its path from the repo root, what
+/// `generate_types` writes and the staleness test compares against.
+pub fn generated_files() -> [(&'static str, String); 2] {
+    [
+        (ARTICLE_SCHEMA_PATH, article_json_schema()),
+        (TYPESCRIPT_PATH, typescript_definitions()),
+    ]
+}
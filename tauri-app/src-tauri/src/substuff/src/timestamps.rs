@@ -0,0 +1,224 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, SubsecRound, Utc};
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Offset-less formats, taken as UTC.
+const NAIVE_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+/// What stands for "no date" in existing articles.
+fn is_unset(value: &str) -> bool {
+    matches!(value, "" | "None" | "null")
+}
+
+/// Parses the timestamp formats found in articles: ISO-8601 from the editor,
+/// `str(datetime)` from the Python exporter and plain dates. Precision is cut
+/// to milliseconds so a parsed value survives `format_timestamp` unchanged.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    let parsed = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%:z"))
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NAIVE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+                .map(|naive| naive.and_utc())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|naive| naive.and_utc())
+        })?;
+    Some(parsed.trunc_subsecs(3))
+}
+
+/// The current time at the precision articles store.
+pub(crate) fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(3)
+}
+
+/// ISO-8601 in UTC with milliseconds, what `Date.toISOString()` gives.
+pub fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// A quoted string, or a bare TOML datetime like `createdAt = 2024-12-19`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTimestamp {
+    Text(String),
+    Toml(toml::value::Datetime),
+}
+
+impl RawTimestamp {
+    fn into_string(self) -> String {
+        match self {
+            RawTimestamp::Text(text) => text,
+            RawTimestamp::Toml(datetime) => datetime.to_string(),
+        }
+    }
+}
+
+fn parse_or_error<E: serde::de::Error>(value: &str) -> Result<DateTime<Utc>, E> {
+    parse_timestamp(value).ok_or_else(|| {
+        E::custom(format!(
+            "{:?} is not a date, expected e.g. 2024-12-19T04:50:03.912Z or 2024-12-19",
+            value
+        ))
+    })
+}
+
+/// Sets an unset `createdAt` to `publishedAt`. The Python exporter wrote
+/// `"None"` for articles it had no creation date for, those were created
+/// when they were published.
+pub(crate) fn default_created_at(document: &mut toml::Table) {
+    let unset = match document.get("createdAt") {
+        None => true,
+        Some(toml::Value::String(value)) => is_unset(value.trim()),
+        Some(_) => false,
+    };
+    if !unset {
+        return;
+    }
+    if let Some(published_at) = document.get("publishedAt").cloned() {
+        log::warn!("The article has no createdAt, using publishedAt");
+        document.insert("createdAt".to_string(), published_at);
+    }
+}
+
+/// `#[serde(with)]` module for a required timestamp.
+pub(crate) mod timestamp {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_timestamp(timestamp))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let value = RawTimestamp::deserialize(deserializer)?.into_string();
+        parse_or_error(&value)
+    }
+}
+
+/// `#[serde(with)]` module for a timestamp that may be missing, `null`, empty
+/// or `"None"`. Pair it with `#[serde(default)]`.
+pub(crate) mod optional_timestamp {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match timestamp {
+            Some(timestamp) => serializer.serialize_str(&format_timestamp(timestamp)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Option::<RawTimestamp>::deserialize(deserializer)?.map(RawTimestamp::into_string) {
+            Some(value) if !is_unset(value.trim()) => parse_or_error(&value).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(millis: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 12, 19, 4, 50, 3).unwrap()
+            + chrono::Duration::milliseconds(millis.into())
+    }
+
+    fn round_trips(value: &str, expected: DateTime<Utc>) {
+        let parsed = parse_timestamp(value).unwrap_or_else(|| panic!("{:?} didn't parse", value));
+        assert_eq!(parsed, expected, "{:?}", value);
+        assert_eq!(parse_timestamp(&format_timestamp(&parsed)), Some(parsed));
+    }
+
+    #[test]
+    fn every_accepted_format_round_trips() {
+        round_trips("2024-12-19T04:50:03.912Z", at(912));
+        round_trips("2024-12-19T05:50:03.912+01:00", at(912));
+        round_trips("2024-12-19 04:50:03.912345+00:00", at(912));
+        round_trips("2024-12-19 04:50:03+00:00", at(0));
+        round_trips("2024-12-19T04:50:03.912", at(912));
+        round_trips("2024-12-19 04:50:03.912345", at(912));
+        round_trips(" 2024-12-19 04:50:03 ", at(0));
+        round_trips(
+            "2024-12-19",
+            Utc.with_ymd_and_hms(2024, 12, 19, 0, 0, 0).unwrap(),
+        );
+        assert_eq!(format_timestamp(&at(912)), "2024-12-19T04:50:03.912Z");
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_date() {
+        for value in ["", "None", "yesterday", "2024-13-01", "19/12/2024"] {
+            assert_eq!(parse_timestamp(value), None, "{:?}", value);
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Dates {
+        #[serde(rename = "createdAt", with = "timestamp")]
+        created_at: DateTime<Utc>,
+        #[serde(rename = "updatedAt", default, with = "optional_timestamp")]
+        updated_at: Option<DateTime<Utc>>,
+    }
+
+    #[test]
+    fn bare_toml_datetimes_and_unset_values_deserialize() {
+        let dates: Dates =
+            toml::from_str("createdAt = 2024-12-19T04:50:03.912Z\nupdatedAt = \"None\"").unwrap();
+        assert_eq!(dates.created_at, at(912));
+        assert_eq!(dates.updated_at, None);
+
+        let dates: Dates = toml::from_str("createdAt = 2024-12-19\nupdatedAt = \"\"").unwrap();
+        assert_eq!(
+            dates.created_at,
+            Utc.with_ymd_and_hms(2024, 12, 19, 0, 0, 0).unwrap()
+        );
+        assert_eq!(dates.updated_at, None);
+    }
+
+    #[test]
+    fn unset_created_at_falls_back_to_published_at() {
+        let mut document: toml::Table =
+            toml::from_str("createdAt = \"None\"\npublishedAt = \"2024-12-19T04:50:03.912Z\"")
+                .unwrap();
+        default_created_at(&mut document);
+        assert_eq!(
+            document["createdAt"].as_str(),
+            Some("2024-12-19T04:50:03.912Z")
+        );
+
+        let mut document: toml::Table = toml::from_str(
+            "createdAt = \"2024-01-01\"\npublishedAt = \"2024-12-19T04:50:03.912Z\"",
+        )
+        .unwrap();
+        default_created_at(&mut document);
+        assert_eq!(document["createdAt"].as_str(), Some("2024-01-01"));
+    }
+
+    #[test]
+    fn exported_article_without_created_at_loads() {
+        let toml = include_str!("../testing.toml").replace(
+            "createdAt = \"2024-12-19T04:50:03.912Z\"",
+            "createdAt = \"None\"",
+        );
+        let article = crate::article_from_toml_str(&toml).unwrap();
+        assert_eq!(article.created_at, article.published_at);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::config::AppConfig;
+use anyhow::{Context, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Keyring service name the token is filed under.
+const KEYRING_SERVICE: &str = "TheBeakersProfessorApp";
+/// Format tag at the start of the fallback file.
+const FILE_MAGIC: &[u8] = b"BKT1";
+const NONCE_LEN: usize = 12;
+
+/// Where a saved token ended up.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenBackend {
+    Keyring,
+    EncryptedFile,
+}
+
+/// Keeps the forge access token between launches.
+///
+/// The OS keyring (Keychain, Credential Manager, Secret Service) is used when
+/// it is available. Headless Linux often has no Secret Service running, so
+/// then the token goes to `token-<forge>.enc` in the app config dir instead,
+/// encrypted with a key derived from the machine id and user name. That keeps
+/// it out of plain sight and useless on another machine, but anyone able to
+/// run code as the same user can decrypt it.
+#[derive(Debug, Clone)]
+pub struct TokenStore {
+    account: String,
+    /// Part of the file key, so another user's copy doesn't decrypt.
+    user: String,
+    file_path: PathBuf,
+}
+
+impl TokenStore {
+    /// One token per forge, so switching the config between github.com and a
+    /// Gitea instance doesn't send a token to the wrong server.
+    pub fn new(config_dir: &Path, config: &AppConfig) -> Self {
+        let account_hash = Sha256::digest(config.web_base_url.as_bytes());
+        let file_name = format!(
+            "token-{}.enc",
+            account_hash[..4]
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        );
+        TokenStore {
+            account: config.web_base_url.clone(),
+            user: user_name(),
+            file_path: config_dir.join(file_name),
+        }
+    }
+
+    fn entry(&self) -> keyring::Result<keyring::Entry> {
+        keyring::Entry::new(KEYRING_SERVICE, &self.account)
+    }
+
+    /// The saved token, `None` if nothing has been saved yet.
+    pub fn load(&self) -> Result<Option<String>> {
+        match self.entry().and_then(|entry| entry.get_password()) {
+            Ok(token) => return Ok(Some(token)),
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => log::warn!("Keyring unavailable, checking {:?}: {}", self.file_path, e),
+        }
+        self.load_file()
+    }
+
+    /// The token in the fallback file. One that can't be decrypted is as good
+    /// as none, the user logs in again and it gets overwritten.
+    fn load_file(&self) -> Result<Option<String>> {
+        if !self.file_path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(&self.file_path)
+            .with_context(|| format!("Failed to read the file: {:?}", self.file_path))?;
+        match self.decrypt(&data) {
+            Ok(token) => Ok(Some(token)),
+            Err(e) => {
+                log::warn!("Ignoring the saved token: {:#}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn save(&self, token: &str) -> Result<TokenBackend> {
+        match self.entry().and_then(|entry| entry.set_password(token)) {
+            Ok(()) => {
+                // Don't leave an older copy behind in the fallback file
+                self.remove_file()?;
+                log::info!("Saved token to the OS keyring");
+                Ok(TokenBackend::Keyring)
+            }
+            Err(e) => {
+                log::warn!("Keyring unavailable, using {:?}: {}", self.file_path, e);
+                self.save_file(token)?;
+                Ok(TokenBackend::EncryptedFile)
+            }
+        }
+    }
+
+    /// Removes the token from the keyring and the fallback file.
+    pub fn clear(&self) -> Result<()> {
+        match self.entry().and_then(|entry| entry.delete_credential()) {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => log::warn!("Could not clear the keyring entry: {}", e),
+        }
+        self.remove_file()
+    }
+
+    fn save_file(&self, token: &str) -> Result<()> {
+        self.write_file(&self.encrypt(token)?)
+    }
+
+    fn remove_file(&self) -> Result<()> {
+        match fs::remove_file(&self.file_path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Could not delete {:?}", self.file_path)),
+        }
+    }
+
+    fn write_file(&self, data: &[u8]) -> Result<()> {
+        if let Some(parent) = self.file_path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Cant Make Dir {:?}", parent))?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&self.file_path)
+            .with_context(|| format!("Could not open {:?}", self.file_path))?;
+        std::io::Write::write_all(&mut file, data)
+            .with_context(|| format!("Could not write {:?}", self.file_path))
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        let mut hasher = Sha256::new();
+        hasher.update(KEYRING_SERVICE.as_bytes());
+        hasher.update(machine_id().as_bytes());
+        hasher.update(self.user.as_bytes());
+        hasher.update(self.account.as_bytes());
+        ChaCha20Poly1305::new(Key::from_slice(&hasher.finalize()))
+    }
+
+    fn encrypt(&self, token: &str) -> Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, token.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt the token"))?;
+        Ok([FILE_MAGIC, nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn decrypt(&self, data: &[u8]) -> Result<String> {
+        let rest = data
+            .strip_prefix(FILE_MAGIC)
+            .filter(|rest| rest.len() > NONCE_LEN)
+            .with_context(|| format!("{:?} is not a token file", self.file_path))?;
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Could not decrypt {:?}, it was written on another machine or by another user",
+                    self.file_path
+                )
+            })?;
+        String::from_utf8(plaintext).context("Decrypted token is not valid UTF-8")
+    }
+}
+
+fn machine_id() -> String {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn user_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(dir: &Path, account: &str, user: &str) -> TokenStore {
+        TokenStore {
+            account: account.to_string(),
+            user: user.to_string(),
+            file_path: dir.join("token.enc"),
+        }
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path(), "https://github.com", "ada");
+        assert_eq!(store.load_file().unwrap(), None);
+
+        store.save_file("gho_secret").unwrap();
+        let data = fs::read(&store.file_path).unwrap();
+        assert!(data.starts_with(FILE_MAGIC));
+        assert!(!data.windows(10).any(|w| w == b"gho_secret"));
+        assert_eq!(store.load_file().unwrap().as_deref(), Some("gho_secret"));
+
+        store.remove_file().unwrap();
+        assert_eq!(store.load_file().unwrap(), None);
+    }
+
+    #[test]
+    fn decrypt_rejects_data_without_magic_or_too_short() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path(), "https://github.com", "ada");
+        let data = store.encrypt("gho_secret").unwrap();
+        assert!(store.decrypt(&data[FILE_MAGIC.len()..]).is_err());
+        assert!(store
+            .decrypt(&[b"BKT2", &data[FILE_MAGIC.len()..]].concat())
+            .is_err());
+        assert!(store
+            .decrypt(&data[..FILE_MAGIC.len() + NONCE_LEN])
+            .is_err());
+        assert!(store.decrypt(b"").is_err());
+    }
+
+    #[test]
+    fn another_account_or_user_cannot_decrypt() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = store(dir.path(), "https://github.com", "ada")
+            .encrypt("gho_secret")
+            .unwrap();
+        assert!(store(dir.path(), "https://gitea.example", "ada")
+            .decrypt(&data)
+            .is_err());
+        assert!(store(dir.path(), "https://github.com", "bob")
+            .decrypt(&data)
+            .is_err());
+    }
+
+    #[test]
+    fn corrupt_file_is_no_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path(), "https://github.com", "ada");
+        store.write_file(b"not a token file").unwrap();
+        assert_eq!(store.load_file().unwrap(), None);
+
+        let mut data = store.encrypt("gho_secret").unwrap();
+        *data.last_mut().unwrap() ^= 1;
+        store.write_file(&data).unwrap();
+        assert_eq!(store.load_file().unwrap(), None);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::body::check_body_formulas;
+use crate::{has_unsafe_markup, reading_time_diverges, Article, GitHubError};
+use serde::Serialize;
+use ts_rs::TS;
+
+/// How bad a `Diagnostic` is. Only `Error` stops an upload.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// One problem with an article.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, TS)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Path of the field using the TOML names, e.g. `questions[1].correct_answer`.
+    pub field: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(
+        severity: Severity,
+        field: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Diagnostic {
+            severity,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    fn error(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0
+            .push(Diagnostic::new(Severity::Error, field, message));
+    }
+
+    fn warning(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0
+            .push(Diagnostic::new(Severity::Warning, field, message));
+    }
+
+    fn required(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.error(field, "Must not be empty");
+        }
+    }
+
+    /// Slugs end up in website URLs, e.g. `/authors/<slug>`. Exported articles
+    /// have ones like `CO_AUTHOR`, so only an empty slug is an error.
+    fn slug(&mut self, field: &str, value: &str) {
+        if value.is_empty() {
+            self.error(field, "Must not be empty");
+        } else if !value
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            self.warning(
+                field,
+                format!(
+                    "{:?} should only contain lowercase letters, digits, '-' and '_'",
+                    value
+                ),
+            );
+        }
+    }
+}
+
+/// Checks an article for problems the TOML parser lets through: empty
+/// required fields, bad slugs, out of order dates and quiz questions without
+/// a valid correct answer. Unparseable dates already fail deserialization.
+///
+/// Returns every problem found, most severe first. An article is fit for
+/// upload when none of them is a `Severity::Error`.
+pub fn validate(article: &Article) -> Vec<Diagnostic> {
+    let mut found = Diagnostics::default();
+
+    found.required("title", &article.title);
+    if article.description.trim().is_empty() {
+        found.warning(
+            "description",
+            "Shown in article previews, consider adding one",
+        );
+    }
+    found.required("body", &article.body);
+    if has_unsafe_markup(&article.body) {
+        found.warning(
+            "body",
+            "Scripts, embeds and inline event handlers are removed on upload",
+        );
+    }
+    found
+        .0
+        .extend(check_body_formulas(&article.body, article.body_format));
+    found.required("category", &article.category);
+    let computed = article.reading_stats().minutes;
+    if article.reading_time == 0 {
+        found.warning("readingTime", "Reading time is 0 minutes");
+    } else if reading_time_diverges(article.reading_time, computed) {
+        found.warning(
+            "readingTime",
+            format!(
+                "The body reads in about {} min, not {}",
+                computed, article.reading_time
+            ),
+        );
+    }
+
+    if article.image.url.trim().is_empty() {
+        found.warning("image.url", "The article has no image");
+    } else if article.image.alt.trim().is_empty() {
+        found.warning("image.alt", "Describe the image for screen readers");
+    }
+
+    if article.authors.is_empty() {
+        found.error("authors", "An article needs at least one author");
+    }
+    for (i, author) in article.authors.iter().enumerate() {
+        found.required(&format!("authors[{}].name", i), &author.name);
+        found.slug(&format!("authors[{}].slug", i), &author.slug);
+    }
+
+    found.required("professor.name", &article.professor.name);
+    found.slug("professor.slug", &article.professor.slug);
+
+    for (i, question) in article.questions.iter().flatten().enumerate() {
+        let field = format!("questions[{}]", i);
+        found.required(&format!("{}.question", field), &question.question);
+        if question.answers.len() < 2 {
+            found.error(
+                format!("{}.answers", field),
+                "A question needs at least two answers",
+            );
+        }
+        for (j, answer) in question.answers.iter().enumerate() {
+            found.required(&format!("{}.answers[{}]", field, j), answer);
+            if question.answers[..j].contains(answer) && !answer.trim().is_empty() {
+                found.warning(
+                    format!("{}.answers[{}]", field, j),
+                    format!("{:?} is listed more than once", answer),
+                );
+            }
+        }
+        if !question.answers.contains(&question.correct_answer) {
+            found.error(
+                format!("{}.correct_answer", field),
+                format!(
+                    "{:?} is not one of the answers, copy it exactly",
+                    question.correct_answer
+                ),
+            );
+        }
+    }
+
+    let created_at = article.created_at;
+    if article.published_at < created_at {
+        found.error("publishedAt", "Published before it was created");
+    }
+    if article
+        .updated_at
+        .is_some_and(|updated_at| updated_at < created_at)
+    {
+        found.warning("updatedAt", "Updated before it was created");
+    }
+    if article
+        .last_updated_at
+        .is_some_and(|last_updated_at| last_updated_at < created_at)
+    {
+        found.warning("lastUpdatedAt", "Updated before it was created");
+    }
+
+    let mut diagnostics = found.0;
+    diagnostics.sort_by_key(|d| std::cmp::Reverse(d.severity));
+    diagnostics
+}
+
+/// Runs `validate` and refuses with `GitHubError::InvalidArticle` if it found
+/// any errors. Warnings are logged and let through.
+pub fn ensure_article_valid(article: &Article) -> Result<(), GitHubError> {
+    let diagnostics = validate(article);
+    for diagnostic in &diagnostics {
+        if diagnostic.severity == Severity::Warning {
+            log::warn!(
+                "{}: {} ({})",
+                article.title,
+                diagnostic.message,
+                diagnostic.field
+            );
+        }
+    }
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Err(GitHubError::InvalidArticle { diagnostics });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::article_from_toml_str;
+    use chrono::Duration;
+
+    const VALID: &str = r#"
+title = "Water"
+description = "Why water is wet."
+body = "<p>Water is H<sub>2</sub>O.</p>"
+category = "chemistry"
+createdAt = "2024-12-19T04:50:03.912Z"
+publishedAt = "2024-12-20T08:00:00.000Z"
+readingTime = 1
+
+[image]
+url = "https://placecat.com/640/360"
+alt = "A cat"
+caption = "A cat"
+
+[[authors]]
+name = "Ada Author"
+authorBio = "Writes."
+slug = "ada-author"
+
+[[questions]]
+question = "What is water?"
+answers = ["H2O", "CO2"]
+correct_answer = "H2O"
+
+[professor]
+name = "Dr. Pro"
+professorBio = "Teaches."
+slug = "dr-pro"
+"#;
+
+    fn article() -> Article {
+        article_from_toml_str(VALID).unwrap()
+    }
+
+    /// `(field, severity)` of every diagnostic, in the order `validate` sorts them.
+    fn found(article: &Article) -> Vec<(String, Severity)> {
+        validate(article)
+            .into_iter()
+            .map(|d| (d.field, d.severity))
+            .collect()
+    }
+
+    #[test]
+    fn a_complete_article_has_no_diagnostics() {
+        assert_eq!(found(&article()), vec![]);
+    }
+
+    #[test]
+    fn empty_title_is_an_error() {
+        let mut article = article();
+        article.title = "  ".to_string();
+        assert_eq!(
+            found(&article),
+            vec![("title".to_string(), Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn correct_answer_must_be_one_of_the_answers() {
+        let mut article = article();
+        article.questions.as_mut().unwrap()[0].correct_answer = "h2o".to_string();
+        assert_eq!(
+            found(&article),
+            vec![("questions[0].correct_answer".to_string(), Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn unparseable_created_at_fails_to_load() {
+        let toml = VALID.replace("2024-12-19T04:50:03.912Z", "last Tuesday");
+        let error = format!("{:#}", article_from_toml_str(&toml).unwrap_err());
+        assert!(error.contains("createdAt"), "{}", error);
+        assert!(
+            error.contains("\"last Tuesday\" is not a date"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn published_before_created_is_an_error() {
+        let mut article = article();
+        article.published_at = article.created_at - Duration::days(1);
+        assert_eq!(
+            found(&article),
+            vec![("publishedAt".to_string(), Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn updated_before_created_is_a_warning() {
+        let mut article = article();
+        article.updated_at = Some(article.created_at - Duration::days(1));
+        assert_eq!(
+            found(&article),
+            vec![("updatedAt".to_string(), Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn errors_sort_before_warnings() {
+        let mut article = article();
+        article.description = String::new();
+        article.title = String::new();
+        assert_eq!(
+            found(&article),
+            vec![
+                ("title".to_string(), Severity::Error),
+                ("description".to_string(), Severity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_errors_stop_an_upload() {
+        let mut article = article();
+        article.description = String::new();
+        article.professor.slug = "Dr_Pro".to_string();
+        assert!(ensure_article_valid(&article).is_ok());
+
+        article.authors.clear();
+        match ensure_article_valid(&article) {
+            Err(GitHubError::InvalidArticle { diagnostics }) => {
+                assert_eq!(diagnostics.len(), 3);
+                assert_eq!(diagnostics[0].field, "authors");
+                assert_eq!(diagnostics[0].severity, Severity::Error);
+            }
+            other => panic!("expected InvalidArticle, got {:?}", other),
+        }
+    }
+}
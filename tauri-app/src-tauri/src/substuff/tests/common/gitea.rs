@@ -0,0 +1,236 @@
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use substuff::{AppConfig, ForgeKind, GiteaForge};
+
+const REPO: &str = "/api/v1/repos/o/r";
+
+type Files = BTreeMap<String, Vec<u8>>;
+
+#[derive(Default)]
+struct Repo {
+    /// Oldest first, `main` points at the last one.
+    commits: Vec<(String, Files)>,
+    /// Committed right before the next change request is handled, like a
+    /// push from someone else landing in between.
+    edit_before_next_change: Option<(String, Vec<u8>)>,
+}
+
+impl Repo {
+    fn head(&self) -> &(String, Files) {
+        self.commits.last().unwrap()
+    }
+
+    fn files_at(&self, git_ref: &str) -> Option<&Files> {
+        if git_ref == "main" {
+            return Some(&self.head().1);
+        }
+        self.commits
+            .iter()
+            .find(|(id, _)| id == git_ref)
+            .map(|(_, files)| files)
+    }
+
+    fn commit(&mut self, files: Files) -> String {
+        let id = format!("commit{}", self.commits.len());
+        self.commits.push((id.clone(), files));
+        id
+    }
+}
+
+pub fn blob_sha(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A Gitea API stand-in serving one repo `o/r` with a `main` branch from
+/// memory, enough of it for `GiteaForge`'s file and commit calls.
+pub struct MockGitea {
+    repo: Arc<Mutex<Repo>>,
+    pub api_base_url: String,
+}
+
+impl MockGitea {
+    /// Serves `files` as the single commit on `main`.
+    pub fn start(files: &[(&str, &[u8])]) -> Self {
+        let mut repo = Repo::default();
+        repo.commit(
+            files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_vec()))
+                .collect(),
+        );
+        let repo = Arc::new(Mutex::new(repo));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api_base_url = format!("http://{}/api/v1", listener.local_addr().unwrap());
+        let served = repo.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                handle(stream.unwrap(), &served);
+            }
+        });
+        MockGitea { repo, api_base_url }
+    }
+
+    pub fn forge(&self) -> GiteaForge {
+        let config = AppConfig {
+            forge: ForgeKind::Gitea,
+            owner: "o".to_string(),
+            repo: "r".to_string(),
+            api_base_url: self.api_base_url.clone(),
+            web_base_url: self.api_base_url.trim_end_matches("/api/v1").to_string(),
+            ..AppConfig::default()
+        };
+        GiteaForge::new(config, reqwest::Client::new())
+    }
+
+    /// The file on `main`.
+    pub fn file(&self, path: &str) -> Option<Vec<u8>> {
+        self.repo.lock().unwrap().head().1.get(path).cloned()
+    }
+
+    pub fn commit_count(&self) -> usize {
+        self.repo.lock().unwrap().commits.len()
+    }
+
+    /// Someone else pushes `content` to `path` just before the next change
+    /// request arrives.
+    pub fn edit_before_next_change(&self, path: &str, content: &[u8]) {
+        self.repo.lock().unwrap().edit_before_next_change =
+            Some((path.to_string(), content.to_vec()));
+    }
+}
+
+fn handle(stream: TcpStream, repo: &Mutex<Repo>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+            content_length = value.trim().parse().unwrap();
+        }
+        if line == "\r\n" || line.is_empty() {
+            break;
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query: BTreeMap<&str, &str> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+    let (status, response) = route(&mut repo.lock().unwrap(), method, path, &query, &body);
+    let response = response.to_string();
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    )
+    .unwrap();
+}
+
+fn route(
+    repo: &mut Repo,
+    method: &str,
+    path: &str,
+    query: &BTreeMap<&str, &str>,
+    body: &Value,
+) -> (&'static str, Value) {
+    let not_found = ("404 Not Found", json!({ "message": "not found" }));
+    let Some(path) = path.strip_prefix(REPO) else {
+        return not_found;
+    };
+    match (method, path) {
+        ("GET", "/branches/main") => (
+            "200 OK",
+            json!({ "name": "main", "commit": { "id": repo.head().0 } }),
+        ),
+        ("GET", "/git/trees/main") => {
+            let tree: Vec<Value> = repo
+                .head()
+                .1
+                .iter()
+                .map(|(path, content)| {
+                    json!({ "path": path, "type": "blob", "sha": blob_sha(content), "size": content.len() })
+                })
+                .collect();
+            ("200 OK", json!({ "tree": tree, "truncated": false }))
+        }
+        ("GET", _) if path.starts_with("/contents/") => {
+            let file_path = &path["/contents/".len()..];
+            let git_ref = query.get("ref").copied().unwrap_or("main");
+            match repo
+                .files_at(git_ref)
+                .and_then(|files| files.get(file_path))
+            {
+                Some(content) => (
+                    "200 OK",
+                    json!({ "sha": blob_sha(content), "content": BASE64_STANDARD.encode(content) }),
+                ),
+                None => not_found,
+            }
+        }
+        ("POST", "/contents") => change_files(repo, body),
+        _ => not_found,
+    }
+}
+
+/// `POST /contents`, Gitea's ChangeFiles: every update has to name the SHA
+/// of the file it replaces on the branch, a create fails if it exists.
+fn change_files(repo: &mut Repo, body: &Value) -> (&'static str, Value) {
+    if let Some((path, content)) = repo.edit_before_next_change.take() {
+        let mut files = repo.head().1.clone();
+        files.insert(path, content);
+        repo.commit(files);
+    }
+    let mut files = repo.head().1.clone();
+    for operation in body["files"].as_array().unwrap() {
+        let path = operation["path"].as_str().unwrap();
+        let from_path = operation["from_path"].as_str().unwrap_or(path);
+        let content = BASE64_STANDARD
+            .decode(operation["content"].as_str().unwrap())
+            .unwrap();
+        match operation["operation"].as_str().unwrap() {
+            "create" if files.contains_key(path) => {
+                return (
+                    "422 Unprocessable Entity",
+                    json!({ "message": "repository file already exists" }),
+                )
+            }
+            "update" => match files.get(from_path) {
+                None => return ("404 Not Found", json!({ "message": "file does not exist" })),
+                Some(existing)
+                    if Some(blob_sha(existing).as_str()) != operation["sha"].as_str() =>
+                {
+                    return ("409 Conflict", json!({ "message": "sha does not match" }))
+                }
+                Some(_) => {
+                    files.remove(from_path);
+                }
+            },
+            _ => {}
+        }
+        files.insert(path.to_string(), content);
+    }
+    let id = repo.commit(files);
+    ("201 Created", json!({ "commit": { "sha": id } }))
+}
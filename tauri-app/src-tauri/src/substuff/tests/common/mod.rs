@@ -0,0 +1,105 @@
+// Every test binary compiles this module and uses a different part of it
+#![allow(dead_code)]
+
+pub mod gitea;
+
+use git2::{Repository, Signature};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+pub const BRANCH: &str = "main";
+
+/// A bare "origin" holding one commit with two articles, like the website repo.
+pub struct Origin {
+    pub dir: TempDir,
+}
+
+impl Origin {
+    pub fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+        let bare = Repository::init_bare(dir.path().join("origin.git")).unwrap();
+        bare.set_head("refs/heads/main").unwrap();
+
+        let seed = Repository::init(dir.path().join("seed")).unwrap();
+        seed.set_head("refs/heads/main").unwrap();
+        write(&seed, "articles/a.toml", "title = \"A\"\n");
+        write(&seed, "articles/b.toml", "title = \"B\"\n");
+        commit_all(&seed, "Add articles");
+        seed.remote("origin", bare.path().to_str().unwrap())
+            .unwrap()
+            .push(&["refs/heads/main:refs/heads/main"], None)
+            .unwrap();
+        Origin { dir }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.path().join("origin.git")
+    }
+
+    /// A fresh clone in `name` next to the bare repo.
+    pub fn clone(&self, name: &str) -> Repository {
+        Repository::clone(self.path().to_str().unwrap(), self.dir.path().join(name)).unwrap()
+    }
+
+    /// Where `main` points in the bare repo.
+    pub fn head(&self) -> git2::Oid {
+        Repository::open_bare(self.path())
+            .unwrap()
+            .refname_to_id("refs/heads/main")
+            .unwrap()
+    }
+}
+
+pub fn write(repo: &Repository, path: &str, content: &str) {
+    let file = repo.workdir().unwrap().join(path);
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(file, content).unwrap();
+}
+
+pub fn read(repo: &Repository, path: &str) -> String {
+    fs::read_to_string(repo.workdir().unwrap().join(path)).unwrap()
+}
+
+pub fn commit_all(repo: &Repository, message: &str) -> git2::Oid {
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("Test", "test@example.com").unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .unwrap()
+}
+
+pub fn author() -> substuff::CommitAuthor {
+    substuff::CommitAuthor {
+        name: "Test".to_string(),
+        email: "test@example.com".to_string(),
+    }
+}
+
+/// Edits `path` in a new clone and publishes it, as another editor would.
+pub fn publish_from_elsewhere(origin: &Origin, name: &str, path: &str, content: &str) {
+    let mut other = origin.clone(name);
+    write(&other, path, content);
+    substuff::publish_local_changes(
+        &mut other,
+        BRANCH,
+        &author(),
+        "Edit elsewhere",
+        None,
+        &Default::default(),
+    )
+    .unwrap();
+}
@@ -0,0 +1,18 @@
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn generated_types_are_up_to_date() {
+    let repo_root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../..");
+    for (path, generated) in substuff::generated_files() {
+        let checked_in = fs::read_to_string(repo_root.join(path))
+            .unwrap_or_default()
+            .replace("\r\n", "\n");
+        assert!(
+            checked_in == generated,
+            "{} is out of date with the Rust types, run `cargo run --bin generate_types` \
+             in tauri-app/src-tauri/src/substuff and commit the result",
+            path
+        );
+    }
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{author, publish_from_elsewhere, read, write, Origin, BRANCH};
+use std::path::PathBuf;
+use substuff::{publish_local_changes, update_repo_preserving_changes, GitProgressHandler};
+
+#[test]
+fn sync_replays_local_edits_on_the_new_tip() {
+    let origin = Origin::new();
+    let mut repo = origin.clone("local");
+    write(&repo, "articles/b.toml", "title = \"B, edited locally\"\n");
+    publish_from_elsewhere(
+        &origin,
+        "other",
+        "articles/a.toml",
+        "title = \"A, upstream\"\n",
+    );
+
+    let report =
+        update_repo_preserving_changes(&mut repo, BRANCH, &GitProgressHandler::default()).unwrap();
+
+    assert_eq!(report.reapplied, vec![PathBuf::from("articles/b.toml")]);
+    assert!(report.conflicts.is_empty());
+    assert_eq!(read(&repo, "articles/a.toml"), "title = \"A, upstream\"\n");
+    assert_eq!(
+        read(&repo, "articles/b.toml"),
+        "title = \"B, edited locally\"\n"
+    );
+    assert_eq!(repo.head().unwrap().target(), Some(origin.head()));
+
+    // The draft branch keeps the edit on top of the old tip
+    let draft = repo
+        .find_branch(
+            report.draft_branch.as_deref().unwrap(),
+            git2::BranchType::Local,
+        )
+        .unwrap();
+    let draft_tree = draft.get().peel_to_tree().unwrap();
+    let entry = draft_tree.get_path("articles/b.toml".as_ref()).unwrap();
+    let blob = repo.find_blob(entry.id()).unwrap();
+    assert_eq!(blob.content(), b"title = \"B, edited locally\"\n");
+}
+
+#[test]
+fn sync_without_local_edits_makes_no_draft() {
+    let origin = Origin::new();
+    let mut repo = origin.clone("local");
+    publish_from_elsewhere(
+        &origin,
+        "other",
+        "articles/a.toml",
+        "title = \"A, upstream\"\n",
+    );
+
+    let report =
+        update_repo_preserving_changes(&mut repo, BRANCH, &GitProgressHandler::default()).unwrap();
+
+    assert_eq!(report.draft_branch, None);
+    assert!(report.reapplied.is_empty());
+    assert_eq!(read(&repo, "articles/a.toml"), "title = \"A, upstream\"\n");
+}
+
+#[test]
+fn sync_conflict_leaves_markers_and_blocks_publishing() {
+    let origin = Origin::new();
+    let mut repo = origin.clone("local");
+    write(&repo, "articles/a.toml", "title = \"A, edited locally\"\n");
+    publish_from_elsewhere(
+        &origin,
+        "other",
+        "articles/a.toml",
+        "title = \"A, upstream\"\n",
+    );
+    let upstream = origin.head();
+
+    let report =
+        update_repo_preserving_changes(&mut repo, BRANCH, &GitProgressHandler::default()).unwrap();
+
+    assert_eq!(report.conflicts, vec![PathBuf::from("articles/a.toml")]);
+    assert!(report.reapplied.is_empty());
+    assert!(report.draft_branch.is_some());
+    let content = read(&repo, "articles/a.toml");
+    assert!(content.contains("<<<<<<< origin/main"), "{}", content);
+    assert!(content.contains("title = \"A, upstream\""), "{}", content);
+    assert!(
+        content.contains("title = \"A, edited locally\""),
+        "{}",
+        content
+    );
+
+    let refused = publish_local_changes(
+        &mut repo,
+        BRANCH,
+        &author(),
+        "Publish",
+        None,
+        &GitProgressHandler::default(),
+    )
+    .unwrap_err();
+    assert!(
+        refused.message().contains("articles/a.toml"),
+        "{}",
+        refused.message()
+    );
+    assert_eq!(origin.head(), upstream);
+
+    // Once resolved it goes through
+    write(&repo, "articles/a.toml", "title = \"A, merged\"\n");
+    let published = publish_local_changes(
+        &mut repo,
+        BRANCH,
+        &author(),
+        "Publish",
+        None,
+        &GitProgressHandler::default(),
+    )
+    .unwrap();
+    assert_eq!(origin.head().to_string(), published.commit_sha);
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::gitea::{blob_sha, MockGitea};
+use substuff::{
+    migrate_remote_articles, schema_version, upgrade_article_toml, Forge, GitHubError,
+    CURRENT_SCHEMA_VERSION,
+};
+
+/// The start of a PNG, not valid UTF-8.
+const PNG: &[u8] = &[
+    0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0xff, 0x00, 0xfe,
+];
+
+#[tokio::test]
+async fn move_keeps_binary_content() {
+    let gitea = MockGitea::start(&[("static/images/old.png", PNG)]);
+    gitea
+        .forge()
+        .move_file(
+            "token",
+            "static/images/old.png",
+            "static/images/new.png",
+            "Rename image",
+            &blob_sha(PNG),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(gitea.file("static/images/old.png"), None);
+    assert_eq!(gitea.file("static/images/new.png").as_deref(), Some(PNG));
+}
+
+#[tokio::test]
+async fn move_refuses_a_changed_file() {
+    let gitea = MockGitea::start(&[("static/images/old.png", PNG)]);
+    let error = gitea
+        .forge()
+        .move_file(
+            "token",
+            "static/images/old.png",
+            "static/images/new.png",
+            "Rename image",
+            &blob_sha(b"an older version"),
+        )
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(&error, GitHubError::Conflict { remote_sha, .. } if *remote_sha == blob_sha(PNG)),
+        "{:?}",
+        error
+    );
+    assert_eq!(gitea.commit_count(), 1);
+    assert_eq!(gitea.file("static/images/old.png").as_deref(), Some(PNG));
+}
+
+#[tokio::test]
+async fn move_refuses_to_overwrite() {
+    let gitea = MockGitea::start(&[("a.png", PNG), ("b.png", b"other")]);
+    let error = gitea
+        .forge()
+        .move_file("token", "a.png", "b.png", "Rename image", &blob_sha(PNG))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, GitHubError::Conflict { .. }), "{:?}", error);
+    assert_eq!(gitea.file("b.png").as_deref(), Some(&b"other"[..]));
+}
+
+/// An article from before `schemaVersion`.
+const EXPORTED: &str = include_str!("../testing.toml");
+
+#[tokio::test]
+async fn migration_commits_only_outdated_articles() {
+    let current = upgrade_article_toml(EXPORTED).unwrap().unwrap();
+    let gitea = MockGitea::start(&[
+        ("src/articles/old.toml", EXPORTED.as_bytes()),
+        ("src/articles/new.toml", current.as_bytes()),
+        ("src/articles/notes.md", b"not an article"),
+    ]);
+    let summary = migrate_remote_articles(&gitea.forge(), "token")
+        .await
+        .unwrap();
+
+    assert_eq!(summary.migrated, ["src/articles/old.toml"]);
+    assert_eq!(summary.up_to_date, 1);
+    assert!(summary.unreadable.is_empty());
+    assert!(summary.committed.is_some());
+    assert_eq!(gitea.commit_count(), 2);
+    let migrated = String::from_utf8(gitea.file("src/articles/old.toml").unwrap()).unwrap();
+    let migrated: toml::Table = toml::from_str(&migrated).unwrap();
+    assert_eq!(schema_version(&migrated).unwrap(), CURRENT_SCHEMA_VERSION);
+}
+
+#[tokio::test]
+async fn migration_has_nothing_to_commit_when_current() {
+    let current = upgrade_article_toml(EXPORTED).unwrap().unwrap();
+    let gitea = MockGitea::start(&[("src/articles/new.toml", current.as_bytes())]);
+    let summary = migrate_remote_articles(&gitea.forge(), "token")
+        .await
+        .unwrap();
+
+    assert!(summary.migrated.is_empty());
+    assert!(summary.committed.is_none());
+    assert_eq!(gitea.commit_count(), 1);
+}
+
+#[tokio::test]
+async fn migration_does_not_overwrite_a_concurrent_edit() {
+    let gitea = MockGitea::start(&[("src/articles/old.toml", EXPORTED.as_bytes())]);
+    gitea.edit_before_next_change("src/articles/old.toml", b"title = \"Edited\"\n");
+    let error = migrate_remote_articles(&gitea.forge(), "token")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, GitHubError::Conflict { .. }), "{:?}", error);
+    assert_eq!(
+        gitea.file("src/articles/old.toml").as_deref(),
+        Some(&b"title = \"Edited\"\n"[..])
+    );
+}
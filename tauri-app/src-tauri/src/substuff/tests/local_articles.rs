@@ -0,0 +1,28 @@
+mod common;
+
+use common::{write, Origin};
+use std::path::Path;
+use substuff::local_blob_sha;
+
+#[test]
+fn local_sha_is_the_committed_blob_not_the_edit() {
+    let origin = Origin::new();
+    let repo = origin.clone("local");
+    let committed = repo
+        .head()
+        .unwrap()
+        .peel_to_tree()
+        .unwrap()
+        .get_path(Path::new("articles/a.toml"))
+        .unwrap()
+        .id()
+        .to_string();
+    write(&repo, "articles/a.toml", "title = \"A, edited\"\n");
+    write(&repo, "articles/new.toml", "title = \"New\"\n");
+
+    let sha = local_blob_sha(&repo, Path::new("articles/a.toml")).unwrap();
+    let new_sha = local_blob_sha(&repo, Path::new("articles/new.toml")).unwrap();
+
+    assert_eq!(sha, Some(committed));
+    assert_eq!(new_sha, None);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{author, publish_from_elsewhere, read, write, Origin, BRANCH};
+use std::path::PathBuf;
+use substuff::{publish_local_changes, GitProgressHandler};
+
+#[test]
+fn publish_pushes_one_commit_to_origin() {
+    let origin = Origin::new();
+    let mut repo = origin.clone("local");
+    write(&repo, "articles/a.toml", "title = \"A, edited\"\n");
+    write(&repo, "articles/c.toml", "title = \"C\"\n");
+
+    let published = publish_local_changes(
+        &mut repo,
+        BRANCH,
+        &author(),
+        "Publish two articles",
+        None,
+        &GitProgressHandler::default(),
+    )
+    .unwrap();
+
+    assert_eq!(origin.head().to_string(), published.commit_sha);
+    let mut files = published.files.clone();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            PathBuf::from("articles/a.toml"),
+            PathBuf::from("articles/c.toml")
+        ]
+    );
+    let commit = repo
+        .find_commit(git2::Oid::from_str(&published.commit_sha).unwrap())
+        .unwrap();
+    assert_eq!(commit.message(), Some("Publish two articles"));
+    assert_eq!(commit.author().name(), Some("Test"));
+    assert!(repo.statuses(None).unwrap().is_empty());
+}
+
+#[test]
+fn publish_without_changes_is_refused() {
+    let origin = Origin::new();
+    let mut repo = origin.clone("local");
+    let before = origin.head();
+
+    let refused = publish_local_changes(
+        &mut repo,
+        BRANCH,
+        &author(),
+        "Nothing",
+        None,
+        &GitProgressHandler::default(),
+    );
+
+    assert!(refused.is_err());
+    assert_eq!(origin.head(), before);
+}
+
+#[test]
+fn rejected_push_undoes_the_local_commit() {
+    let origin = Origin::new();
+    let mut repo = origin.clone("local");
+    let local_head = repo.head().unwrap().target().unwrap();
+    publish_from_elsewhere(
+        &origin,
+        "other",
+        "articles/b.toml",
+        "title = \"B, upstream\"\n",
+    );
+    let upstream = origin.head();
+    write(&repo, "articles/a.toml", "title = \"A, edited\"\n");
+
+    let rejected = publish_local_changes(
+        &mut repo,
+        BRANCH,
+        &author(),
+        "Publish",
+        None,
+        &GitProgressHandler::default(),
+    )
+    .unwrap_err();
+
+    assert_eq!(origin.head(), upstream, "{}", rejected.message());
+    assert_eq!(repo.head().unwrap().target(), Some(local_head));
+    // The edit is a plain working tree change again
+    assert_eq!(read(&repo, "articles/a.toml"), "title = \"A, edited\"\n");
+    let status = repo.status_file("articles/a.toml".as_ref()).unwrap();
+    assert_eq!(status, git2::Status::WT_MODIFIED);
+}
@@ -10,4 +10,16 @@ export const selected_answers = writable<string[]>([]);
 export const articleStore = writable<Article | null>(null);
 
 // Store for the GitHub token
-export const githubTokenStore = writable<string | null>(null);
\ No newline at end of file
+export const githubTokenStore = writable<string | null>(null);
+
+// Where the article in articleStore was opened from, null for a new article.
+// `sha` goes back with the upload so edits made elsewhere aren't overwritten,
+// it is null for a local file that was never committed.
+export const articleSourceStore = writable<{
+	path: string;
+	fileName: string;
+	sha: string | null;
+} | null>(null);
+
+// Whether uploads replace readingTime with the one computed from the body
+export const computeReadingTimeStore = writable<boolean>(true);